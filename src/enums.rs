@@ -4,15 +4,17 @@
 
 #![allow(dead_code)]
 
-use crate::parsers::parse_escape;
-use heapless::Vec;
+use crate::parsers::{parse_escape, truncate};
 use std::fmt;
+use unicode_segmentation::UnicodeSegmentation as _;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token<'a> {
     Text(&'a str),
     C0(C0),
     EscapeSequence(EscapeSequence),
+    OperatingSystemCommand(OperatingSystemCommand),
+    ControlString(ControlString),
 }
 
 impl<'a> fmt::Display for Token<'a> {
@@ -22,6 +24,8 @@ impl<'a> fmt::Display for Token<'a> {
             Text(txt) => write!(f, "{}", txt),
             C0(c0) => write!(f, "{:?}", c0),
             EscapeSequence(escape_sequence) => write!(f, "{:?}", escape_sequence),
+            OperatingSystemCommand(osc) => write!(f, "{:?}", osc),
+            ControlString(control_string) => write!(f, "{:?}", control_string),
         }
     }
 }
@@ -67,71 +71,377 @@ pub enum C0 {
 // TODO figure out defaults
 #[derive(Debug, PartialEq, Clone)]
 pub enum EscapeSequence {
-    ICH(u32),              // [#@ def 1 Insert CHaracter
-    CUU(u32),              // [#A def 1 CUrsor Up
-    CUD(u32),              // [#B def 1 CUrsor Down
-    CUF(u32),              // [#C def 1 CUrsor Forward
-    CUB(u32),              // [#D def 1 CUrsor Backward
-    CNL(u32),              // [#E def 1 Cursor to Next Line
-    CPL(u32),              // [#F def 1 Cursor to Previous Line
-    CHA(u32),              // [#G def 1 Cursor Horizontal position Absolute
-    CUP(u32, u32),         // [#;#H def 1;1 CUrsor Position
-    CHT(u32),              // [#I def 1 Cursor Horizontal Tabulation
-    ED(u32),               // [#J def 0 Erase in Display
-    EL(u32),               // [#K def 0 Erase in Line
-    IL(u32),               // [#L def 0 Insert Line, current line moves down
-    DL(u32),               // [#M def 0 Delete Line, lines below current move up
-    EF(u32),               // [#N def 0 Erase in Field
-    EA(u32),               // [#O def 0 Erase in qualified Area
-    DCH(u32),              // [#P def 1 Delete CHaracter
-    SEM(u32),              // [#Q def 0 Set Editing extent Mode
-    CPR,                   // [R        Cursor Position Report
-    SU(u32),               // [#S def 1 Scroll Up
-    SD(u32),               // [#T def 1 Scroll Down
-    NP(u32),               // [#U def 1 Next Page
-    PP(u32),               // [#V def 1 Previous Page
-    CTC(u32),              // [#W def 0 Cursor Tabulation Control
-    ECH(u32),              // [#X def 1 Erase CHaracter
-    CVT(u32),              // [#Y def 1 Cursor Vertical Tab
-    CBT(u32),              // [#Z def 1 Cursor Back Tab
-    HPA(u32),              // [#` def 0 Horizontal Position Absolute
-    HPR(u32),              // [#a def 0 Horizontal Position Relative
-    REP(u32),              // [#b def 1 REPeat previous displayable character
-    DA,                    // [c        Device Attributes
-    VPA(u32),              // [#d def 0 Vertical Position Absolute
-    VPR(u32),              // [#e def 0 Vertical Position Relative
-    HVP(u32, u32),         // [#;#f def 0;0 Horizontal and Vertical Position
-    TBC(u32),              // [#g def 0 TaBulation Clear
-    SM(u32),               // [#h def 0 Set Mode
-    MC(u32),               // [#i def 0 Media Copy
-    PageFormatSelect(u32), // [#j def 0
-    RM,                    // [l       Reset Mode
-    SGR(Vec<u8, 5>),       // [#;#;#;#;#l def 0 Set Graphics Rendition
-    DSR(u32),              // [#n def 0 Device Status Report
-    DAQ(u32),              // [#o def 0 Define Area Qualification starting at current position
-    DECLL,                 // [q UNIMPLEMENTED many params
-    DECSTBM(u32, u32),     // [#;#r def 1;1 top and bottom margins
-    DECSTRM(u32, u32),     // [#;#s def 1;1 left and right margins
-    DECSLPP(u32),          // [#t def 66 physical lines per page
-    DECSHTS,               // [u        UNIMPLEMENTED many params
-    DECSVTS,               // [v        UNIMPLEMENTED many params
-    DECSHORP(u32),         // [#w def 0 set horizontal pitch on LAxxx printers
-    DECREQTPARM,           // [x UNIMPLEMENTED many params
-    DECTST(u32, u32),      // [#;#y def 2;1 invoke confidence test
-    DECVERP(u32),          // [#z def 0 set vertical -pitch on LA100
-    DECTTC(u32),           // [#| def 0 transmit termination character
-    DECPRO,                // [} UNIMPLEMENTED many params
-    DECKEYS(u32),          // [#~ def 0 sent by special function keys
-    DELETE,                // [DELETE   always ignored
-    SL(u32),               // [# @ def 1 Scroll Left
-    SR(u32),               // [# A def 1 Scroll Right
-    GSM(u32, u32),         // [#;# B def 100;100 Graphic Size Modification
-    GSS(u32),              // [# C def 720 Graphic Size Selection
-    FNT(u32, u32),         // [#;# D def 0;1 FoNT selection
-    TSS(u32),              // [# E def 720 Thin Space Specification
-    JFY(u32),              // [# F def 0 JustiFY
-    SPI(u32, u32),         // [#;# G def 720;720 SPacing Increment
-    QUAD(u32),             // [# H def 0 do QUADding on current line of text
+    ICH(u32),               // [#@ def 1 Insert CHaracter
+    CUU(u32),               // [#A def 1 CUrsor Up
+    CUD(u32),               // [#B def 1 CUrsor Down
+    CUF(u32),               // [#C def 1 CUrsor Forward
+    CUB(u32),               // [#D def 1 CUrsor Backward
+    CNL(u32),               // [#E def 1 Cursor to Next Line
+    CPL(u32),               // [#F def 1 Cursor to Previous Line
+    CHA(u32),               // [#G def 1 Cursor Horizontal position Absolute
+    CUP(u32, u32),          // [#;#H def 1;1 CUrsor Position
+    CHT(u32),               // [#I def 1 Cursor Horizontal Tabulation
+    ED(u32),                // [#J def 0 Erase in Display
+    EL(u32),                // [#K def 0 Erase in Line
+    IL(u32),                // [#L def 0 Insert Line, current line moves down
+    DL(u32),                // [#M def 0 Delete Line, lines below current move up
+    EF(u32),                // [#N def 0 Erase in Field
+    EA(u32),                // [#O def 0 Erase in qualified Area
+    DCH(u32),               // [#P def 1 Delete CHaracter
+    SEM(u32),               // [#Q def 0 Set Editing extent Mode
+    CPR,                    // [R        Cursor Position Report
+    SU(u32),                // [#S def 1 Scroll Up
+    SD(u32),                // [#T def 1 Scroll Down
+    NP(u32),                // [#U def 1 Next Page
+    PP(u32),                // [#V def 1 Previous Page
+    CTC(u32),               // [#W def 0 Cursor Tabulation Control
+    ECH(u32),               // [#X def 1 Erase CHaracter
+    CVT(u32),               // [#Y def 1 Cursor Vertical Tab
+    CBT(u32),               // [#Z def 1 Cursor Back Tab
+    HPA(u32),               // [#` def 0 Horizontal Position Absolute
+    HPR(u32),               // [#a def 0 Horizontal Position Relative
+    REP(u32),               // [#b def 1 REPeat previous displayable character
+    DA,                     // [c        Device Attributes, primary
+    VPA(u32),               // [#d def 0 Vertical Position Absolute
+    VPR(u32),               // [#e def 0 Vertical Position Relative
+    HVP(u32, u32),          // [#;#f def 0;0 Horizontal and Vertical Position
+    TBC(u32),               // [#g def 0 TaBulation Clear
+    SM(Vec<u32>),           // [#;#h Set Mode
+    MC(u32),                // [#i def 0 Media Copy
+    PageFormatSelect(u32),  // [#j def 0
+    RM(Vec<u32>),           // [#;#l Reset Mode
+    SGR(Vec<SgrAttribute>), // [#;#;...m def 0 Set Graphics Rendition
+    DSR(u32),               // [#n def 0 Device Status Report
+    DAQ(u32),               // [#o def 0 Define Area Qualification starting at current position
+    DECLL,                  // [q UNIMPLEMENTED many params
+    DECSTBM(u32, u32),      // [#;#r def 1;0 top and bottom margins, 0 being the last line
+    DECSTRM(u32, u32),      // [#;#s def 1;0 left and right margins, 0 being the last column
+    DECSLPP(u32),           // [#t def 66 physical lines per page
+    DECSHTS,                // [u        UNIMPLEMENTED many params
+    DECSVTS,                // [v        UNIMPLEMENTED many params
+    DECSHORP(u32),          // [#w def 0 set horizontal pitch on LAxxx printers
+    DECREQTPARM,            // [x UNIMPLEMENTED many params
+    DECTST(u32, u32),       // [#;#y def 2;1 invoke confidence test
+    DECVERP(u32),           // [#z def 0 set vertical -pitch on LA100
+    DECTTC(u32),            // [#| def 0 transmit termination character
+    DECPRO,                 // [} UNIMPLEMENTED many params
+    DECKEYS(u32),           // [#~ def 0 sent by special function keys
+    DELETE,                 // [DELETE   always ignored
+    SL(u32),                // [# @ def 1 Scroll Left
+    SR(u32),                // [# A def 1 Scroll Right
+    GSM(u32, u32),          // [#;# B def 100;100 Graphic Size Modification
+    GSS(u32),               // [# C def 720 Graphic Size Selection
+    FNT(u32, u32),          // [#;# D def 0;1 FoNT selection
+    TSS(u32),               // [# E def 720 Thin Space Specification
+    JFY(u32),               // [# F def 0 JustiFY
+    SPI(u32, u32),          // [#;# G def 720;720 SPacing Increment
+    QUAD(u32),              // [# H def 0 do QUADding on current line of text
+    DECSCUSR(u32),          // [# q def 0 Set CUrsor Style
+    DECSET(Vec<u32>),       // [?#;#h DEC private mode SET
+    DECRST(Vec<u32>),       // [?#;#l DEC private mode ReSeT
+    RQM(u32),               // [#$p ReQuest ANSI Mode
+    DA2,                    // [>c Device Attributes, secondary
+    DA3,                    // [=c Device Attributes, tertiary
+    XTVERSION,              // [>q report the terminal's name and version
+    DECRQM(u32),            // [?#$p DEC ReQuest private Mode
+    DECSTR,                 // [!p DEC Soft Terminal Reset
+    DECSC,                  // 7 DEC Save Cursor
+    DECRC,                  // 8 DEC Restore Cursor
+    IND,                    // D INDex
+    NEL,                    // E NExt Line
+    HTS,                    // H Horizontal Tab Set
+    RI,                     // M Reverse Index
+    RIS,                    // c Reset to Initial State
+    DECKPAM,                // = DEC KeyPad Application Mode
+    DECKPNM,                // > DEC KeyPad Numeric Mode
+    SCS(usize, Charset),    // (# )# *# +# Select Character Set into G0 to G3
+    LS2,                    // n Locking Shift G2 into GL
+    LS3,                    // o Locking Shift G3 into GL
+    SS2,                    // N Single Shift G2 for the next character
+    SS3,                    // O Single Shift G3 for the next character
+    DECALN,                 // #8 DEC screen ALigNment test
+    Unrecognised(Csi),      // any other well formed control sequence
+}
+
+/// character sets designated by `EscapeSequence::SCS`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Charset {
+    #[default]
+    UsAscii, // B
+    DecSpecialGraphics, // 0
+    British,            // A
+    Dutch,              // 4
+    Finnish,            // C or 5
+    French,             // R or f
+    FrenchCanadian,     // Q or 9
+    German,             // K
+    Italian,            // Y
+    NorwegianDanish,    // E, 6 or `
+    Spanish,            // Z
+    Swedish,            // H or 7
+    Swiss,              // =
+}
+
+impl Charset {
+    /// the character `chr` stands for in this set\
+    /// characters the set does not replace are returned unchanged
+    pub fn map(self, chr: char) -> char {
+        use Charset::*;
+        let replacements: &[(char, char)] = match self {
+            UsAscii => &[],
+            DecSpecialGraphics => &[
+                ('_', ' '),
+                ('`', '◆'),
+                ('a', '▒'),
+                ('b', '␉'),
+                ('c', '␌'),
+                ('d', '␍'),
+                ('e', '␊'),
+                ('f', '°'),
+                ('g', '±'),
+                ('h', '␤'),
+                ('i', '␋'),
+                ('j', '┘'),
+                ('k', '┐'),
+                ('l', '┌'),
+                ('m', '└'),
+                ('n', '┼'),
+                ('o', '⎺'),
+                ('p', '⎻'),
+                ('q', '─'),
+                ('r', '⎼'),
+                ('s', '⎽'),
+                ('t', '├'),
+                ('u', '┤'),
+                ('v', '┴'),
+                ('w', '┬'),
+                ('x', '│'),
+                ('y', '≤'),
+                ('z', '≥'),
+                ('{', 'π'),
+                ('|', '≠'),
+                ('}', '£'),
+                ('~', '·'),
+            ],
+            British => &[('#', '£')],
+            Dutch => &[
+                ('#', '£'),
+                ('@', '¾'),
+                ('[', 'ĳ'),
+                ('\\', '½'),
+                (']', '|'),
+                ('{', '¨'),
+                ('|', 'ƒ'),
+                ('}', '¼'),
+                ('~', '´'),
+            ],
+            Finnish => &[
+                ('[', 'Ä'),
+                ('\\', 'Ö'),
+                (']', 'Å'),
+                ('^', 'Ü'),
+                ('`', 'é'),
+                ('{', 'ä'),
+                ('|', 'ö'),
+                ('}', 'å'),
+                ('~', 'ü'),
+            ],
+            French => &[
+                ('#', '£'),
+                ('@', 'à'),
+                ('[', '°'),
+                ('\\', 'ç'),
+                (']', '§'),
+                ('{', 'é'),
+                ('|', 'ù'),
+                ('}', 'è'),
+                ('~', '¨'),
+            ],
+            FrenchCanadian => &[
+                ('@', 'à'),
+                ('[', 'â'),
+                ('\\', 'ç'),
+                (']', 'ê'),
+                ('^', 'î'),
+                ('`', 'ô'),
+                ('{', 'é'),
+                ('|', 'ù'),
+                ('}', 'è'),
+                ('~', 'û'),
+            ],
+            German => &[
+                ('@', '§'),
+                ('[', 'Ä'),
+                ('\\', 'Ö'),
+                (']', 'Ü'),
+                ('{', 'ä'),
+                ('|', 'ö'),
+                ('}', 'ü'),
+                ('~', 'ß'),
+            ],
+            Italian => &[
+                ('#', '£'),
+                ('@', '§'),
+                ('[', '°'),
+                ('\\', 'ç'),
+                (']', 'é'),
+                ('`', 'ù'),
+                ('{', 'à'),
+                ('|', 'ò'),
+                ('}', 'è'),
+                ('~', 'ì'),
+            ],
+            NorwegianDanish => &[
+                ('@', 'Ä'),
+                ('[', 'Æ'),
+                ('\\', 'Ø'),
+                (']', 'Å'),
+                ('^', 'Ü'),
+                ('`', 'ä'),
+                ('{', 'æ'),
+                ('|', 'ø'),
+                ('}', 'å'),
+                ('~', 'ü'),
+            ],
+            Spanish => &[
+                ('#', '£'),
+                ('@', '§'),
+                ('[', '¡'),
+                ('\\', 'Ñ'),
+                (']', '¿'),
+                ('{', '°'),
+                ('|', 'ñ'),
+                ('}', 'ç'),
+            ],
+            Swedish => &[
+                ('@', 'É'),
+                ('[', 'Ä'),
+                ('\\', 'Ö'),
+                (']', 'Å'),
+                ('^', 'Ü'),
+                ('`', 'é'),
+                ('{', 'ä'),
+                ('|', 'ö'),
+                ('}', 'å'),
+                ('~', 'ü'),
+            ],
+            Swiss => &[
+                ('#', 'ù'),
+                ('@', 'à'),
+                ('[', 'é'),
+                ('\\', 'ç'),
+                (']', 'ê'),
+                ('^', 'î'),
+                ('_', 'è'),
+                ('`', 'ô'),
+                ('{', 'ä'),
+                ('|', 'ö'),
+                ('}', 'ü'),
+                ('~', 'û'),
+            ],
+        };
+        replacements
+            .iter()
+            .find(|(from, _)| *from == chr)
+            .map_or(chr, |(_, to)| *to)
+    }
+}
+
+/// a control sequence as laid out by ECMA-48:\
+/// `ESC [`, an optional private marker, parameter bytes, intermediate bytes and a final byte
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Csi {
+    pub private: Option<char>,         // one of `?>=<`
+    pub params: Vec<Vec<Option<u32>>>, // `;` separated groups of `:` separated sub-parameters
+    pub intermediates: String,         // 0x20 to 0x2F
+    pub final_byte: char,              // 0x40 to 0x7E
+}
+
+impl Csi {
+    /// the parameter at `index`, or `default` if it was omitted
+    pub fn param(&self, index: usize, default: u32) -> u32 {
+        self.params
+            .get(index)
+            .and_then(|param| param.first().copied().flatten())
+            .unwrap_or(default)
+    }
+
+    /// every parameter, with `default` for those that were omitted
+    pub fn params_or(&self, default: u32) -> Vec<u32> {
+        self.params
+            .iter()
+            .map(|param| param.first().copied().flatten().unwrap_or(default))
+            .collect()
+    }
+}
+
+/// `ESC ] # ; text` terminated by BEL or ST
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum OperatingSystemCommand {
+    SetIconNameAndWindowTitle(String), // 0
+    SetIconName(String),               // 1
+    SetWindowTitle(String),            // 2
+    Raw(String),                       // anything else, the whole payload between `ESC ]` and ST
+}
+
+/// control strings other than OSC, each holding its payload up to ST
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ControlString {
+    DCS(String), // ESC P Device Control String
+    SOS(String), // ESC X Start Of String
+    PM(String),  // ESC ^ Privacy Message
+    APC(String), // ESC _ Application Program Command
+}
+
+/// the longest control string payload kept by default, in bytes; see `AnsiStreamParser::new`
+pub const DEFAULT_MAX_STRING_LENGTH: usize = 0x10_0000;
+
+/// a single attribute change carried by `EscapeSequence::SGR`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SgrAttribute {
+    Reset,                 // 0
+    Bold,                  // 1
+    Faint,                 // 2
+    Italic,                // 3
+    Underline(Underline),  // 4, 4:# and 21
+    SlowBlink,             // 5
+    RapidBlink,            // 6
+    Inverse,               // 7
+    Conceal,               // 8
+    CrossedOut,            // 9
+    Font(u8),              // 10-19, 0 is the primary font
+    Fraktur,               // 20
+    NormalIntensity,       // 22 neither bold nor faint
+    NotItalic,             // 23 neither italic nor fraktur
+    NotUnderlined,         // 24 and 4:0
+    NotBlinking,           // 25
+    NotInverse,            // 27
+    Reveal,                // 28
+    NotCrossedOut,         // 29
+    Foreground(Color),     // 30-39 and 90-97
+    Background(Color),     // 40-49 and 100-107
+    Overlined,             // 53
+    NotOverlined,          // 55
+    UnderlineColor(Color), // 58 and 59
+}
+
+/// underline styles, from the kitty `4:#` extension
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Underline {
+    Single, // 4 or 4:1
+    Double, // 21 or 4:2
+    Curly,  // 4:3
+    Dotted, // 4:4
+    Dashed, // 4:5
+}
+
+/// colors as set by SGR
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Color {
+    #[default]
+    Default, // 39, 49 and 59
+    Indexed(u8),     // 30-37, 90-97 or 38;5;#
+    Rgb(u8, u8, u8), // 38;2;#;#;#
 }
 
 pub trait AnsiParser {
@@ -163,61 +473,179 @@ impl<'a> Iterator for AnsiParseIterator<'a> {
             return None;
         }
 
-        let (head, tail) = head(self.dat);
-        let dat = self.dat;
+        // the input is complete, so an unfinished escape sequence is just an ESC
+        let (token, tail) = next_token(self.dat, DEFAULT_MAX_STRING_LENGTH)
+            .unwrap_or_else(|| (Token::C0(C0::ESC), head(self.dat).1));
         self.dat = tail;
+        Some(token)
+    }
+}
+
+/// incremental parser for output read from a pty\
+/// partial escape sequences and partial UTF-8 characters at the end of one `feed` are kept
+/// until the next one completes them
+#[derive(Debug)]
+pub struct AnsiStreamParser {
+    /// decoded input, of which the first `consumed` bytes have already been tokenised
+    dat: String,
+    consumed: usize,
+    /// trailing bytes of an incomplete UTF-8 character
+    partial: Vec<u8>,
+    /// control string payloads are truncated to this many bytes
+    max_string_length: usize,
+}
+
+impl Default for AnsiStreamParser {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_STRING_LENGTH)
+    }
+}
+
+impl AnsiStreamParser {
+    /// a parser keeping at most `max_string_length` bytes of each control string payload
+    pub fn new(max_string_length: usize) -> Self {
+        Self {
+            dat: String::new(),
+            consumed: 0,
+            partial: Vec::new(),
+            max_string_length,
+        }
+    }
+
+    /// appends `bytes` to the input and iterates over every token it completes\
+    /// invalid UTF-8 is replaced with U+FFFD like `String::from_utf8_lossy`
+    pub fn feed(&mut self, bytes: &[u8]) -> AnsiStreamIterator<'_> {
+        self.dat.drain(..self.consumed);
+        self.consumed = 0;
+        // whatever is left is an unfinished sequence; drop the middle of an overlong one, which
+        // the parser would truncate anyway, but keep its last character as it may begin ST
+        let keep = truncate(&self.dat, self.max_string_length.saturating_add(2)).len();
+        if let Some((last, _)) = self
+            .dat
+            .char_indices()
+            .next_back()
+            .filter(|(last, _)| *last > keep)
+        {
+            self.dat.drain(keep..last);
+        }
+        self.partial.extend_from_slice(bytes);
 
-        // bound checked
-        use C0::*;
-        Some(match head {
-            "\u{00}" => Token::C0(NUL),
-            "\u{01}" => Token::C0(SOH),
-            "\u{02}" => Token::C0(STX),
-            "\u{03}" => Token::C0(ETX),
-            "\u{04}" => Token::C0(EOT),
-            "\u{05}" => Token::C0(ENQ),
-            "\u{06}" => Token::C0(ACK),
-            "\u{07}" => Token::C0(BEL),
-            "\u{08}" => Token::C0(BS),
-            "\u{09}" => Token::C0(HT),
-            "\u{0a}" => Token::C0(LF),
-            "\u{0b}" => Token::C0(VT),
-            "\u{0c}" => Token::C0(FF),
-            "\u{0d}" => Token::C0(CR),
-            "\u{0e}" => Token::C0(SO),
-            "\u{0f}" => Token::C0(SI),
-            "\u{10}" => Token::C0(DLE),
-            "\u{11}" => Token::C0(DC1),
-            "\u{12}" => Token::C0(DC2),
-            "\u{13}" => Token::C0(DC3),
-            "\u{14}" => Token::C0(DC4),
-            "\u{15}" => Token::C0(NAK),
-            "\u{16}" => Token::C0(SYN),
-            "\u{17}" => Token::C0(ETB),
-            "\u{18}" => Token::C0(CAN),
-            "\u{19}" => Token::C0(EM),
-            "\u{1a}" => Token::C0(SUB),
-            "\u{1b}" => {
-                if let Ok(ret) = parse_escape(dat) {
-                    println!("^[ successfully parsed");
-                    self.dat = ret.0.clone();
-                    Token::EscapeSequence(ret.1)
-                } else {
-                    println!("^[ unimplemented");
-                    Token::C0(ESC)
+        let mut start = 0;
+        while start < self.partial.len() {
+            match std::str::from_utf8(&self.partial[start..]) {
+                Ok(valid) => {
+                    self.dat.push_str(valid);
+                    start = self.partial.len();
+                }
+                Err(error) => {
+                    let valid_up_to = start + error.valid_up_to();
+                    self.dat
+                        .push_str(&String::from_utf8_lossy(&self.partial[start..valid_up_to]));
+                    match error.error_len() {
+                        Some(len) => {
+                            self.dat.push(char::REPLACEMENT_CHARACTER);
+                            start = valid_up_to + len;
+                        }
+                        None => {
+                            start = valid_up_to;
+                            break;
+                        }
+                    }
                 }
             }
-            "\u{1c}" => Token::C0(FS),
-            "\u{1d}" => Token::C0(GS),
-            "\u{1e}" => Token::C0(RS),
-            "\u{1f}" => Token::C0(US),
-            "\u{20}" => Token::C0(SP),
-            "\u{7F}" => Token::C0(DEL),
-            txt => Token::Text(txt),
-        })
+        }
+        self.partial.drain(..start);
+
+        AnsiStreamIterator {
+            dat: &self.dat,
+            consumed: &mut self.consumed,
+            max_string_length: self.max_string_length,
+        }
+    }
+}
+
+/// iterator over the tokens completed by `AnsiStreamParser::feed`
+#[derive(Debug)]
+pub struct AnsiStreamIterator<'a> {
+    dat: &'a str,
+    consumed: &'a mut usize,
+    max_string_length: usize,
+}
+
+impl<'a> Iterator for AnsiStreamIterator<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let dat = self.dat.get(*self.consumed..)?;
+        if dat.is_empty() {
+            return None;
+        }
+
+        let (token, tail) = next_token(dat, self.max_string_length)?;
+        *self.consumed = self.dat.len() - tail.len();
+        Some(token)
     }
 }
 
+/// splits the first token off of a non-empty `dat`\
+/// returns `None` if `dat` ends partway through an escape sequence
+fn next_token(dat: &str, max_string_length: usize) -> Option<(Token<'_>, &str)> {
+    let (head, mut tail) = head(dat);
+
+    // bound checked
+    use C0::*;
+    let token = match head {
+        "\u{00}" => Token::C0(NUL),
+        "\u{01}" => Token::C0(SOH),
+        "\u{02}" => Token::C0(STX),
+        "\u{03}" => Token::C0(ETX),
+        "\u{04}" => Token::C0(EOT),
+        "\u{05}" => Token::C0(ENQ),
+        "\u{06}" => Token::C0(ACK),
+        "\u{07}" => Token::C0(BEL),
+        "\u{08}" => Token::C0(BS),
+        "\u{09}" => Token::C0(HT),
+        "\u{0a}" => Token::C0(LF),
+        "\u{0b}" => Token::C0(VT),
+        "\u{0c}" => Token::C0(FF),
+        "\u{0d}" => Token::C0(CR),
+        "\u{0e}" => Token::C0(SO),
+        "\u{0f}" => Token::C0(SI),
+        "\u{10}" => Token::C0(DLE),
+        "\u{11}" => Token::C0(DC1),
+        "\u{12}" => Token::C0(DC2),
+        "\u{13}" => Token::C0(DC3),
+        "\u{14}" => Token::C0(DC4),
+        "\u{15}" => Token::C0(NAK),
+        "\u{16}" => Token::C0(SYN),
+        "\u{17}" => Token::C0(ETB),
+        "\u{18}" => Token::C0(CAN),
+        "\u{19}" => Token::C0(EM),
+        "\u{1a}" => Token::C0(SUB),
+        "\u{1b}" => match parse_escape(dat, max_string_length) {
+            Ok((rest, token)) => {
+                tail = rest;
+                token
+            }
+            Err(nom::Err::Incomplete(_)) => return None,
+            Err(_) => Token::C0(ESC),
+        },
+        "\u{1c}" => Token::C0(FS),
+        "\u{1d}" => Token::C0(GS),
+        "\u{1e}" => Token::C0(RS),
+        "\u{1f}" => Token::C0(US),
+        "\u{20}" => Token::C0(SP),
+        "\u{7F}" => Token::C0(DEL),
+        // a whole grapheme cluster, so combining marks stay with their base character
+        _ => {
+            let grapheme = dat.graphemes(true).next().unwrap_or(head);
+            tail = &dat[grapheme.len()..];
+            Token::Text(grapheme)
+        }
+    };
+    Some((token, tail))
+}
+
 fn head(string: &str) -> (&str, &str) {
     for i in 1..5 {
         let r = string.get(0..i);
@@ -228,3 +656,77 @@ fn head(string: &str) -> (&str, &str) {
     }
     panic!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_sequence_split_across_feeds() {
+        let mut parser = AnsiStreamParser::default();
+        assert_eq!(parser.feed(b"\x1b[3").collect::<Vec<_>>(), vec![]);
+        assert_eq!(
+            parser.feed(b"1mx").collect::<Vec<_>>(),
+            vec![
+                Token::EscapeSequence(EscapeSequence::SGR(vec![SgrAttribute::Foreground(
+                    Color::Indexed(1)
+                )])),
+                Token::Text("x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn utf8_split_across_feeds() {
+        let mut parser = AnsiStreamParser::default();
+        assert_eq!(
+            parser.feed(b"a\xc3").collect::<Vec<_>>(),
+            vec![Token::Text("a")]
+        );
+        assert_eq!(
+            parser.feed(b"\xa9").collect::<Vec<_>>(),
+            vec![Token::Text("\u{e9}")]
+        );
+    }
+
+    #[test]
+    fn osc_split_before_terminator() {
+        let mut parser = AnsiStreamParser::default();
+        assert_eq!(parser.feed(b"\x1b]2;title").collect::<Vec<_>>(), vec![]);
+        assert_eq!(
+            parser.feed(b"\x07").collect::<Vec<_>>(),
+            vec![Token::OperatingSystemCommand(
+                OperatingSystemCommand::SetWindowTitle(String::from("title"))
+            )]
+        );
+    }
+
+    #[test]
+    fn overlong_control_string_is_truncated() {
+        let mut parser = AnsiStreamParser::new(4);
+        assert_eq!(parser.feed(b"\x1bPabcdef").collect::<Vec<_>>(), vec![]);
+        assert_eq!(parser.feed(b"ghij").collect::<Vec<_>>(), vec![]);
+        assert_eq!(
+            parser.feed(b"\x1b\\x").collect::<Vec<_>>(),
+            vec![
+                Token::ControlString(ControlString::DCS(String::from("abcd"))),
+                Token::Text("x"),
+            ]
+        );
+    }
+
+    // a combining mark starting a feed is its own token; `Model::print` joins it to the
+    // grapheme before the cursor
+    #[test]
+    fn combining_mark_in_next_feed() {
+        let mut parser = AnsiStreamParser::default();
+        assert_eq!(
+            parser.feed("e\u{301}".as_bytes()).collect::<Vec<_>>(),
+            vec![Token::Text("e\u{301}")]
+        );
+        assert_eq!(
+            parser.feed("\u{301}".as_bytes()).collect::<Vec<_>>(),
+            vec![Token::Text("\u{301}")]
+        );
+    }
+}
@@ -0,0 +1,672 @@
+//! the screen grid: fixed rows and columns of styled cells
+
+use crate::enums::{Color, SgrAttribute, Underline};
+
+use std::collections::{VecDeque, vec_deque};
+use std::mem;
+use std::ops::Range;
+
+/// rows of history kept unless configured otherwise
+pub const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+
+/// attribute flags of a cell, as set by SGR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Attributes(u16);
+
+impl Attributes {
+    pub const BOLD: Self = Self(1);
+    pub const FAINT: Self = Self(1 << 1);
+    pub const ITALIC: Self = Self(1 << 2);
+    pub const SLOW_BLINK: Self = Self(1 << 3);
+    pub const RAPID_BLINK: Self = Self(1 << 4);
+    pub const INVERSE: Self = Self(1 << 5);
+    pub const CONCEAL: Self = Self(1 << 6);
+    pub const CROSSED_OUT: Self = Self(1 << 7);
+    pub const FRAKTUR: Self = Self(1 << 8);
+    pub const OVERLINED: Self = Self(1 << 9);
+
+    /// whether every flag of `other` is set
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        return self.0 & other.0 == other.0;
+    }
+
+    /// sets every flag of `other`
+    #[inline]
+    pub const fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// clears every flag of `other`
+    #[inline]
+    pub const fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+/// colors and attributes of a cell, also used as the pen new cells are written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub foreground: Color,
+    pub background: Color,
+    pub underline_color: Color,
+    pub underline: Option<Underline>,
+    pub attributes: Attributes,
+}
+
+impl Style {
+    /// applies a single SGR attribute change
+    #[inline]
+    pub fn apply(&mut self, attribute: SgrAttribute) {
+        match attribute {
+            SgrAttribute::Reset => *self = Self::default(),
+            SgrAttribute::Bold => self.attributes.insert(Attributes::BOLD),
+            SgrAttribute::Faint => self.attributes.insert(Attributes::FAINT),
+            SgrAttribute::Italic => self.attributes.insert(Attributes::ITALIC),
+            SgrAttribute::Underline(underline) => self.underline = Some(underline),
+            SgrAttribute::SlowBlink => self.attributes.insert(Attributes::SLOW_BLINK),
+            SgrAttribute::RapidBlink => self.attributes.insert(Attributes::RAPID_BLINK),
+            SgrAttribute::Inverse => self.attributes.insert(Attributes::INVERSE),
+            SgrAttribute::Conceal => self.attributes.insert(Attributes::CONCEAL),
+            SgrAttribute::CrossedOut => self.attributes.insert(Attributes::CROSSED_OUT),
+            SgrAttribute::Font(_) => (),
+            SgrAttribute::Fraktur => self.attributes.insert(Attributes::FRAKTUR),
+            SgrAttribute::NormalIntensity => {
+                self.attributes.remove(Attributes::BOLD);
+                self.attributes.remove(Attributes::FAINT);
+            }
+            SgrAttribute::NotItalic => {
+                self.attributes.remove(Attributes::ITALIC);
+                self.attributes.remove(Attributes::FRAKTUR);
+            }
+            SgrAttribute::NotUnderlined => self.underline = None,
+            SgrAttribute::NotBlinking => {
+                self.attributes.remove(Attributes::SLOW_BLINK);
+                self.attributes.remove(Attributes::RAPID_BLINK);
+            }
+            SgrAttribute::NotInverse => self.attributes.remove(Attributes::INVERSE),
+            SgrAttribute::Reveal => self.attributes.remove(Attributes::CONCEAL),
+            SgrAttribute::NotCrossedOut => self.attributes.remove(Attributes::CROSSED_OUT),
+            SgrAttribute::Foreground(color) => self.foreground = color,
+            SgrAttribute::Background(color) => self.background = color,
+            SgrAttribute::Overlined => self.attributes.insert(Attributes::OVERLINED),
+            SgrAttribute::NotOverlined => self.attributes.remove(Attributes::OVERLINED),
+            SgrAttribute::UnderlineColor(color) => self.underline_color = color,
+        }
+    }
+
+    /// the style erased cells get: only the background survives
+    #[inline]
+    pub fn blank(self) -> Self {
+        return Self {
+            background: self.background,
+            ..Self::default()
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// first character of the grapheme cluster drawn in the cell
+    pub chr: char,
+    /// the rest of the grapheme cluster, such as combining marks, if there is any
+    pub extra: Option<Box<str>>,
+    /// columns taken by the grapheme: 2 for wide glyphs, 0 for the spacer following one
+    pub width: u8,
+    pub style: Style,
+}
+
+impl Cell {
+    /// a cell holding `grapheme`, taking `width` columns
+    #[inline]
+    pub fn new(grapheme: &str, width: u8, style: Style) -> Self {
+        let mut chars = grapheme.chars();
+        let chr = chars.next().unwrap_or(' ');
+        let rest = chars.as_str();
+        return Self {
+            chr,
+            extra: (!rest.is_empty()).then(|| Box::from(rest)),
+            width,
+            style,
+        };
+    }
+
+    /// an empty cell with the given style
+    #[inline]
+    pub const fn blank(style: Style) -> Self {
+        return Self {
+            chr: ' ',
+            extra: None,
+            width: 1,
+            style,
+        };
+    }
+
+    /// the placeholder in the column covered by the right half of a wide glyph
+    #[inline]
+    pub const fn spacer(style: Style) -> Self {
+        return Self {
+            chr: ' ',
+            extra: None,
+            width: 0,
+            style,
+        };
+    }
+
+    /// whether the cell is covered by the wide glyph to its left
+    #[inline]
+    pub const fn is_spacer(&self) -> bool {
+        return self.width == 0;
+    }
+
+    /// appends zero-width characters, such as combining marks, to the grapheme
+    #[inline]
+    pub fn push_str(&mut self, tail: &str) {
+        let mut extra = self.extra.take().map(String::from).unwrap_or_default();
+        extra.push_str(tail);
+        self.extra = Some(extra.into_boxed_str());
+    }
+
+    /// appends the grapheme to `out`
+    #[inline]
+    pub fn push_to(&self, out: &mut String) {
+        out.push(self.chr);
+        if let Some(extra) = &self.extra {
+            out.push_str(extra);
+        }
+    }
+}
+
+impl Default for Cell {
+    #[inline]
+    fn default() -> Self {
+        return Self::blank(Style::default());
+    }
+}
+
+/// a line of the grid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub cells: Vec<Cell>,
+    /// whether the line continues on the next row because it was wrapped at the last column
+    pub wrapped: bool,
+}
+
+impl Row {
+    /// a row of `columns` copies of `blank`
+    #[inline]
+    pub fn new(columns: usize, blank: Cell) -> Self {
+        return Self {
+            cells: vec![blank; columns],
+            wrapped: false,
+        };
+    }
+
+    /// overwrites the cells in `columns` with `blank`, ending a wrapped line if it reaches the
+    /// last column
+    #[inline]
+    pub fn clear(&mut self, columns: Range<usize>, blank: Cell) {
+        if columns.end >= self.cells.len() {
+            self.wrapped = false;
+        }
+        let columns = self.clamp(columns);
+        if let Some(cells) = self.cells.get_mut(columns) {
+            cells.fill(blank);
+        }
+    }
+
+    /// whether the row holds nothing but default cells and does not continue on the next row
+    #[inline]
+    pub fn is_blank(&self) -> bool {
+        return !self.wrapped
+            && self
+                .cells
+                .iter()
+                .all(|cell| return *cell == Cell::default());
+    }
+
+    /// writes `cell` at `column`, followed by a spacer for each further column it takes\
+    /// wide glyphs only partially overwritten are blanked
+    #[inline]
+    pub fn write(&mut self, column: usize, cell: Cell) {
+        let end = self
+            .clamp(column..column.saturating_add(usize::from(cell.width.max(1))))
+            .end;
+        // the left half of a wide glyph whose spacer is overwritten
+        if self.cells.get(column).is_some_and(Cell::is_spacer) {
+            if let Some(left) = column
+                .checked_sub(1)
+                .and_then(|left| self.cells.get_mut(left))
+            {
+                *left = Cell::blank(left.style);
+            }
+        }
+        // the spacer of a wide glyph whose left half is overwritten
+        if let Some(right) = self.cells.get_mut(end).filter(|right| right.is_spacer()) {
+            *right = Cell::blank(right.style);
+        }
+        let spacer = Cell::spacer(cell.style);
+        if let Some((first, rest)) = self
+            .cells
+            .get_mut(column..end)
+            .and_then(|cells| cells.split_first_mut())
+        {
+            *first = cell;
+            rest.fill(spacer);
+        }
+    }
+
+    /// inserts `count` cells of `blank` at the start of `region`, pushing the cells after right\
+    /// cells pushed past the end of `region` are discarded
+    #[inline]
+    pub fn insert_cells(&mut self, region: Range<usize>, count: usize, blank: Cell) {
+        let region = self.clamp(region);
+        let count = count.min(region.len());
+        if let Some(cells) = self.cells.get_mut(region.clone()) {
+            cells.rotate_right(count);
+        }
+        self.clear(region.start..region.start.saturating_add(count), blank);
+        self.mend_wide_glyphs();
+    }
+
+    /// deletes `count` cells at the start of `region`, pulling the cells after left\
+    /// cells of `blank` fill in at the end of `region`
+    #[inline]
+    pub fn delete_cells(&mut self, region: Range<usize>, count: usize, blank: Cell) {
+        let region = self.clamp(region);
+        let count = count.min(region.len());
+        if let Some(cells) = self.cells.get_mut(region.clone()) {
+            cells.rotate_left(count);
+        }
+        self.clear(region.end.saturating_sub(count)..region.end, blank);
+        self.mend_wide_glyphs();
+    }
+
+    /// blanks the halves of wide glyphs separated from their other half, as happens when cells
+    /// are shifted or copied across the edge of a region
+    fn mend_wide_glyphs(&mut self) {
+        for column in 0..self.cells.len() {
+            let left_is_wide = column
+                .checked_sub(1)
+                .and_then(|left| return self.cells.get(left))
+                .is_some_and(|left| return left.width == 2);
+            let right_is_spacer = self
+                .cells
+                .get(column.saturating_add(1))
+                .is_some_and(Cell::is_spacer);
+            if let Some(cell) = self.cells.get_mut(column) {
+                if (cell.is_spacer() && !left_is_wide) || (cell.width == 2 && !right_is_spacer) {
+                    *cell = Cell::blank(cell.style);
+                }
+            }
+        }
+    }
+
+    /// `region` limited to the cells of the row
+    fn clamp(&self, region: Range<usize>) -> Range<usize> {
+        let end = region.end.min(self.cells.len());
+        return region.start.min(end)..end;
+    }
+}
+
+/// the visible screen: a fixed number of rows of a fixed number of columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    rows: Vec<Row>,
+    columns: usize,
+}
+
+impl Grid {
+    /// a blank grid
+    #[inline]
+    pub fn new(rows: usize, columns: usize) -> Self {
+        return Self {
+            rows: vec![Row::new(columns, Cell::default()); rows],
+            columns,
+        };
+    }
+
+    /// a grid of `rows`, each of which must be `columns` cells long
+    #[inline]
+    pub const fn from_rows(rows: Vec<Row>, columns: usize) -> Self {
+        return Self { rows, columns };
+    }
+
+    /// the rows, from top to bottom
+    #[inline]
+    pub fn into_rows(self) -> Vec<Row> {
+        return self.rows;
+    }
+
+    /// number of rows
+    #[inline]
+    pub fn rows(&self) -> usize {
+        return self.rows.len();
+    }
+
+    /// number of columns
+    #[inline]
+    pub const fn columns(&self) -> usize {
+        return self.columns;
+    }
+
+    /// iterates over the rows from top to bottom
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Row> {
+        return self.rows.iter();
+    }
+
+    /// the row at `row`, if it exists
+    #[inline]
+    pub fn row_mut(&mut self, row: usize) -> Option<&mut Row> {
+        return self.rows.get_mut(row);
+    }
+
+    /// the cell at `row`, `column`, if it exists
+    #[inline]
+    pub fn cell_mut(&mut self, row: usize, column: usize) -> Option<&mut Cell> {
+        return self.rows.get_mut(row)?.cells.get_mut(column);
+    }
+
+    /// overwrites every cell with `blank`
+    #[inline]
+    pub fn clear(&mut self, blank: Cell) {
+        for row in &mut self.rows {
+            row.cells.fill(blank.clone());
+            row.wrapped = false;
+        }
+    }
+
+    /// overwrites every cell of the rows in `rows` with `blank`
+    #[inline]
+    pub fn clear_rows(&mut self, rows: Range<usize>, blank: Cell) {
+        let end = rows.end.min(self.rows.len());
+        for row in self
+            .rows
+            .get_mut(rows.start.min(end)..end)
+            .unwrap_or_default()
+        {
+            row.cells.fill(blank.clone());
+            row.wrapped = false;
+        }
+    }
+
+    /// inserts `count` rows of `blank` at the top of `region`, pushing the rows below down\
+    /// rows pushed past the bottom of `region` are discarded
+    #[inline]
+    pub fn insert_rows(&mut self, region: Range<usize>, count: usize, blank: Cell) {
+        let region = self.clamp(region);
+        let count = count.min(region.len());
+        if let Some(rows) = self.rows.get_mut(region.clone()) {
+            rows.rotate_right(count);
+        }
+        self.clear_rows(region.start..region.start.saturating_add(count), blank);
+    }
+
+    /// deletes `count` rows at the top of `region`, pulling the rows below up\
+    /// rows of `blank` fill in at the bottom of `region`; the deleted rows are returned
+    #[inline]
+    pub fn delete_rows(&mut self, region: Range<usize>, count: usize, blank: Cell) -> Vec<Row> {
+        let region = self.clamp(region);
+        let count = count.min(region.len());
+        let Some(rows) = self.rows.get_mut(region.clone()) else {
+            return Vec::new();
+        };
+        let deleted = rows.get(..count).unwrap_or_default().to_vec();
+        rows.rotate_left(count);
+        self.clear_rows(region.end.saturating_sub(count)..region.end, blank);
+        return deleted;
+    }
+
+    /// scrolls the rows in `region` up by `count`, returning the rows scrolled off the top
+    #[inline]
+    pub fn scroll_up(&mut self, region: Range<usize>, count: usize, blank: Cell) -> Vec<Row> {
+        return self.delete_rows(region, count, blank);
+    }
+
+    /// scrolls the rows in `region` down by `count`
+    #[inline]
+    pub fn scroll_down(&mut self, region: Range<usize>, count: usize, blank: Cell) {
+        self.insert_rows(region, count, blank);
+    }
+
+    /// scrolls the cells of `columns` in the rows of `region` up by `count`, leaving the cells
+    /// outside `columns` alone\
+    /// cells of `blank` fill in at the bottom of the rectangle
+    #[inline]
+    pub fn scroll_rect_up(
+        &mut self,
+        region: Range<usize>,
+        columns: Range<usize>,
+        count: usize,
+        blank: Cell,
+    ) {
+        let region = self.clamp(region);
+        let count = count.min(region.len());
+        for row in region.clone() {
+            let source =
+                Some(row.saturating_add(count)).filter(|source| return *source < region.end);
+            self.copy_rect_row(source, row, columns.clone(), &blank);
+        }
+    }
+
+    /// scrolls the cells of `columns` in the rows of `region` down by `count`, leaving the cells
+    /// outside `columns` alone\
+    /// cells of `blank` fill in at the top of the rectangle
+    #[inline]
+    pub fn scroll_rect_down(
+        &mut self,
+        region: Range<usize>,
+        columns: Range<usize>,
+        count: usize,
+        blank: Cell,
+    ) {
+        let region = self.clamp(region);
+        let count = count.min(region.len());
+        for row in region.clone().rev() {
+            let source = row
+                .checked_sub(count)
+                .filter(|source| return *source >= region.start);
+            self.copy_rect_row(source, row, columns.clone(), &blank);
+        }
+    }
+
+    /// copies the cells of `columns` from row `source` to row `destination`, or clears them with
+    /// `blank` if there is no source
+    fn copy_rect_row(
+        &mut self,
+        source: Option<usize>,
+        destination: usize,
+        columns: Range<usize>,
+        blank: &Cell,
+    ) {
+        let end = columns.end.min(self.columns);
+        let columns = columns.start.min(end)..end;
+        let cells = source
+            .and_then(|source| return self.rows.get(source)?.cells.get(columns.clone()))
+            .map(<[Cell]>::to_vec);
+        let Some(row) = self.rows.get_mut(destination) else {
+            return;
+        };
+        match cells {
+            Some(cells) => {
+                if let Some(destination) = row.cells.get_mut(columns) {
+                    destination.clone_from_slice(&cells);
+                }
+            }
+            None => row.clear(columns, blank.clone()),
+        }
+        row.mend_wide_glyphs();
+    }
+
+    /// resizes the grid, truncating or padding rows on the bottom and cells on the right
+    #[inline]
+    pub fn resize(&mut self, rows: usize, columns: usize) {
+        self.rows.resize(rows, Row::new(columns, Cell::default()));
+        for row in &mut self.rows {
+            row.cells.resize(columns, Cell::default());
+        }
+        self.columns = columns;
+    }
+
+    /// `region` limited to the rows of the grid
+    fn clamp(&self, region: Range<usize>) -> Range<usize> {
+        let end = region.end.min(self.rows.len());
+        return region.start.min(end)..end;
+    }
+}
+
+/// rows scrolled off the top of the screen, oldest first\
+/// once `limit` rows are stored the oldest is dropped for every row added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scrollback {
+    rows: VecDeque<Row>,
+    limit: usize,
+}
+
+impl Scrollback {
+    /// an empty scrollback keeping at most `limit` rows
+    #[inline]
+    pub fn new(limit: usize) -> Self {
+        return Self {
+            rows: VecDeque::with_capacity(limit.min(DEFAULT_SCROLLBACK_LINES)),
+            limit,
+        };
+    }
+
+    /// number of rows stored
+    #[inline]
+    pub fn len(&self) -> usize {
+        return self.rows.len();
+    }
+
+    /// whether no rows are stored
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        return self.rows.is_empty();
+    }
+
+    /// adds a row as the newest, dropping the oldest if full
+    #[inline]
+    pub fn push(&mut self, row: Row) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.rows.len() >= self.limit {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+    }
+
+    /// drops every row
+    #[inline]
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    /// removes every row, oldest first
+    #[inline]
+    pub fn drain(&mut self) -> vec_deque::Drain<'_, Row> {
+        return self.rows.drain(..);
+    }
+
+    /// iterates over the rows in `rows`, oldest first
+    #[inline]
+    pub fn range(&self, rows: Range<usize>) -> vec_deque::Iter<'_, Row> {
+        let end = rows.end.min(self.rows.len());
+        return self.rows.range(rows.start.min(end)..end);
+    }
+}
+
+impl Extend<Row> for Scrollback {
+    #[inline]
+    fn extend<T: IntoIterator<Item = Row>>(&mut self, rows: T) {
+        for row in rows {
+            self.push(row);
+        }
+    }
+}
+
+/// re-wraps `rows` to `columns`, first joining soft-wrapped rows back into the lines they were
+/// wrapped from and dropping the blank cells ending each line\
+/// `cursor` is the row and column of a cell to follow; where it ends up is returned with the rows
+#[inline]
+pub fn reflow(
+    rows: impl IntoIterator<Item = Row>,
+    columns: usize,
+    cursor: (usize, usize),
+) -> (Vec<Row>, (usize, usize)) {
+    let mut reflowed = Vec::new();
+    let mut moved_cursor = (0, 0);
+    let mut line = Vec::new();
+    let mut line_cursor = None;
+    for (index, Row { mut cells, wrapped }) in rows.into_iter().enumerate() {
+        // the blank left at the end of a row by a wide glyph that did not fit on it
+        if cells.first().is_some_and(|cell| return cell.width > 1)
+            && line.last() == Some(&Cell::default())
+        {
+            line.pop();
+        }
+        if index == cursor.0 {
+            line_cursor = Some(line.len().saturating_add(cursor.1));
+        }
+        if !wrapped {
+            // the cursor's cell is kept even if it is blank
+            let keep = if index == cursor.0 {
+                cursor.1.saturating_add(1)
+            } else {
+                0
+            };
+            while cells.len() > keep && cells.last() == Some(&Cell::default()) {
+                cells.pop();
+            }
+        }
+        line.extend(cells);
+        if !wrapped {
+            let line_cursor = line_cursor.take();
+            wrap_line(
+                &mut reflowed,
+                mem::take(&mut line),
+                columns,
+                line_cursor,
+                &mut moved_cursor,
+            );
+        }
+    }
+    if !line.is_empty() {
+        wrap_line(&mut reflowed, line, columns, line_cursor, &mut moved_cursor);
+    }
+    return (reflowed, moved_cursor);
+}
+
+/// appends `line` to `rows` as rows of `columns` cells, all but the last marked as wrapped\
+/// wide glyphs that would straddle two rows move to the second; if `cursor` is the offset of a
+/// cell in `line`, `moved_cursor` is set to where that cell goes
+fn wrap_line(
+    rows: &mut Vec<Row>,
+    line: Vec<Cell>,
+    columns: usize,
+    cursor: Option<usize>,
+    moved_cursor: &mut (usize, usize),
+) {
+    let mut cells = Vec::with_capacity(columns);
+    for (offset, cell) in line.into_iter().enumerate() {
+        let width = if cell.width > 1 { 2 } else { 1 };
+        if !cells.is_empty() && cells.len().saturating_add(width) > columns {
+            cells.resize(columns, Cell::default());
+            rows.push(Row {
+                cells: mem::take(&mut cells),
+                wrapped: true,
+            });
+        }
+        if cursor == Some(offset) {
+            *moved_cursor = (rows.len(), cells.len());
+        }
+        cells.push(cell);
+    }
+    cells.resize(columns, Cell::default());
+    rows.push(Row {
+        cells,
+        wrapped: false,
+    });
+}
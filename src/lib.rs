@@ -17,24 +17,37 @@
 )]
 
 use crate::enums::*;
+use crate::grid::{
+    Attributes, Cell, DEFAULT_SCROLLBACK_LINES, Grid, Row, Scrollback, Style, reflow,
+};
+use crate::modes::{Mode, Modes};
 
 use bpaf::Bpaf;
 
-use iced::widget::{column, rich_text, row, scrollable, span, text};
-use iced::{Element, Task, keyboard, time, window};
+use iced::advanced::graphics;
+use iced::advanced::text::Paragraph as _;
+use iced::widget::{column, container, rich_text, row, span, text};
+use iced::{Element, Length, Task, event, keyboard, mouse, time, window};
+use iced::{advanced, alignment, font, theme};
 
 use nix::errno::Errno;
 use nix::fcntl;
-use nix::pty::{ForkptyResult, forkpty};
+use nix::pty::{ForkptyResult, Winsize, forkpty};
 use nix::unistd::write;
 
+use unicode_width::UnicodeWidthStr as _;
+
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::{self, Read as _};
-use std::os::unix::io::{AsFd as _, OwnedFd};
+use std::ops::Range;
+use std::os::unix::io::{AsFd as _, AsRawFd as _, OwnedFd};
 use std::process::Command;
-use std::{error, fmt, thread, time as core_time};
+use std::{error, fmt, mem, thread, time as core_time};
 
 pub mod enums;
+pub mod grid;
+pub mod modes;
 pub mod parsers;
 
 /// whether to enable verbose logging; see `Flags::verbose`
@@ -49,12 +62,43 @@ static mut VOMIT: bool = false;
 /// shell path; see `Flags::shell`
 static mut SHELL: Option<String> = None;
 
+/// longest control string payload kept; see `Flags::max_string_length`
+static mut MAX_STRING_LENGTH: usize = DEFAULT_MAX_STRING_LENGTH;
+
+/// whether ambiguous width characters are wide; see `Flags::ambiguous_wide`
+static mut AMBIGUOUS_WIDE: bool = false;
+
+/// rows of history kept; see `Flags::scrollback_lines`
+static mut SCROLLBACK_LINES: usize = DEFAULT_SCROLLBACK_LINES;
+
+/// whether the bell flashes the screen; see `Flags::visual_bell`
+static mut VISUAL_BELL: bool = false;
+
+/// whether the bell requests the window manager's attention; see `Flags::urgent_bell`
+static mut URGENT_BELL: bool = false;
+
+/// command run by the bell; see `Flags::bell_command`
+static mut BELL_COMMAND: Option<String> = None;
+
+/// shortest time between two bells, later ones being dropped
+const BELL_INTERVAL: core_time::Duration = core_time::Duration::from_millis(200);
+
+/// how long the visual bell shows the screen in reverse video
+const VISUAL_BELL_DURATION: core_time::Duration = core_time::Duration::from_millis(100);
+
 /// events to be passed to `Model::update`
 #[non_exhaustive]
 #[derive(Debug, Clone)]
 pub enum Msg {
     Exit,
     KeyPressed(keyboard::Key),
+    /// moves the viewport this many lines back into the scrollback, or forward if negative\
+    /// fractions of a line add up over successive messages
+    ScrollLines(f32),
+    /// moves the viewport this many screens back into the scrollback, or forward if negative
+    ScrollPages(isize),
+    /// the window was opened or resized at this size
+    Resized(iced::Size),
     Tick,
 }
 
@@ -62,8 +106,6 @@ pub enum Msg {
 #[non_exhaustive]
 #[derive(Debug)]
 enum Error {
-    /// out of bounds err while accessing a slice
-    IndexOutOfBounds,
     /// io error
     Io(io::Error),
     /// nix crate error
@@ -81,7 +123,6 @@ impl fmt::Display for Error {
             Self::Nix(nix_error) => return write!(f, "{nix_error}"),
             Self::Io(io_error) => return write!(f, "{io_error}"),
             Self::NoFileDescriptor => return write!(f, "no file descriptor specified"),
-            Self::IndexOutOfBounds => return write!(f, "index out of bounds"),
             Self::Unreachable => return write!(f, "unreachable error, panic"),
         }
     }
@@ -117,6 +158,30 @@ pub struct Flags {
     #[bpaf(short('S'), long)]
     shell: Option<String>,
 
+    /// longest OSC, DCS, SOS, PM or APC payload to keep, in bytes
+    #[bpaf(long, argument("BYTES"))]
+    max_string_length: Option<usize>,
+
+    /// treat East Asian ambiguous width characters as wide
+    #[bpaf(long)]
+    ambiguous_wide: bool,
+
+    /// rows of history to keep above the screen
+    #[bpaf(long, argument("LINES"))]
+    scrollback_lines: Option<usize>,
+
+    /// flash the screen on a bell
+    #[bpaf(long)]
+    visual_bell: bool,
+
+    /// mark the window as wanting attention on a bell
+    #[bpaf(long)]
+    urgent_bell: bool,
+
+    /// run this shell command on a bell
+    #[bpaf(long, argument("COMMAND"))]
+    bell_command: Option<String>,
+
     /// no logging, NOOP; log level 0
     #[bpaf(short, long)]
     quiet: bool,
@@ -139,15 +204,35 @@ pub struct Flags {
     version: bool,
 }
 
+/// state saved by DECSC and restored by DECRC
+#[derive(Debug, Clone, Copy, Default)]
+struct SavedCursor {
+    /// zero-based row and column
+    cursor: (usize, usize),
+    /// SGR pen
+    style: Style,
+    /// whether origin mode (DECOM) was set
+    origin: bool,
+    /// character sets designated into G0 to G3
+    charsets: [Charset; 4],
+    /// the G set invoked into GL
+    shift: usize,
+    /// the G set selected for the next character by SS2 or SS3
+    single_shift: Option<usize>,
+}
+
 /// represents the terminal emulator\
 /// example usage:
-/// ```rust
-/// iced::application("window title", Model::update, Model::view)
+/// ```no_run
+/// # use rust_term::Model;
+/// # fn main() -> iced::Result {
+/// iced::application(Model::title, Model::update, Model::view)
 ///     .theme(Model::theme)
 ///     .default_font(iced::Font::MONOSPACE)
 ///     .decorations(false)
 ///     .subscription(Model::subscription)
 ///     .run()
+/// # }
 /// ```
 pub struct Model {
     /// location of cursor in user input line
@@ -158,10 +243,70 @@ pub struct Model {
     input: String,
     /// path to shell
     shell: String,
+    /// parser state carried between reads from the pty
+    parser: AnsiStreamParser,
+    /// window title, set by OSC 0 and 2
+    title: String,
 
-    screen: Vec<Vec<String>>,
+    /// visible cells
+    screen: Grid,
+    /// rows scrolled off the top of the primary screen
+    scrollback: Scrollback,
+    /// how many rows the viewport is scrolled back into `scrollback`, 0 showing the screen
+    scroll_offset: usize,
+    /// fraction of a line the mouse wheel has scrolled beyond `scroll_offset`
+    scroll_remainder: f32,
+    /// the screen not being shown: the alternate one while `alternate_screen` is false and
+    /// the primary one while it is true
+    inactive_screen: Grid,
+    /// whether `screen` is the alternate screen, see modes 47, 1047 and 1049
+    alternate_screen: bool,
+    /// zero-based row and column of the cursor
     cursor: (usize, usize),
+    /// where the cursor was on the primary screen when the alternate one was shown, which
+    /// resizing reflows the primary screen around
+    primary_cursor: (usize, usize),
+    /// whether the last column was just written, so the next character wraps before printing
+    pending_wrap: bool,
+    /// which ANSI and DEC private modes are set
+    modes: Modes,
+    /// rows and columns of `screen`
     dimensions: (usize, usize),
+    /// zero-based top and bottom rows of the scroll region, inclusive; set by DECSTBM
+    margins: (usize, usize),
+    /// zero-based left and right columns of the scroll region, inclusive; set by DECSLRM while
+    /// DECLRMM is set
+    column_margins: (usize, usize),
+    /// pen new cells are written with, set by SGR
+    style: Style,
+    /// the last grapheme printed and the style it was printed in, repeated by REP
+    last_printed: Option<Cell>,
+    /// cursor stored by DECSC and restored by DECRC on the screen being shown
+    saved_cursor: SavedCursor,
+    /// saved cursor of the screen not being shown, as each screen keeps its own
+    inactive_saved_cursor: SavedCursor,
+    /// whether each column has a tab stop, set by HTS and cleared by TBC
+    tab_stops: Vec<bool>,
+    /// character sets designated into G0 to G3
+    charsets: [Charset; 4],
+    /// the G set invoked into GL: G0 after SI, G1 after SO, G2 after LS2 and G3 after LS3
+    shift: usize,
+    /// the G set selected for the next character by SS2 or SS3
+    single_shift: Option<usize>,
+    /// whether East Asian ambiguous width characters take two columns
+    ambiguous_wide: bool,
+    /// whether a bell flashes the screen
+    visual_bell: bool,
+    /// whether a bell requests the window manager's attention
+    urgent_bell: bool,
+    /// shell command run by a bell
+    bell_command: Option<String>,
+    /// when the last bell that was not dropped rang
+    last_bell: Option<core_time::Instant>,
+    /// when the visual bell stops showing the screen in reverse video
+    flash_until: Option<core_time::Instant>,
+    /// whether a bell is waiting for `update` to request the window manager's attention
+    attention_requested: bool,
 }
 
 impl Model {
@@ -185,10 +330,42 @@ impl Model {
         let tick = time::every(time::Duration::new(0, 1)).map(|_| {
             return Msg::Tick;
         });
-        let key = keyboard::on_key_press(|key, _| {
+        let key = keyboard::on_key_press(|key, modifiers| {
+            if modifiers.shift() {
+                match key {
+                    keyboard::Key::Named(keyboard::key::Named::PageUp) => {
+                        return Some(Msg::ScrollPages(1));
+                    }
+                    keyboard::Key::Named(keyboard::key::Named::PageDown) => {
+                        return Some(Msg::ScrollPages(-1));
+                    }
+                    _ => (),
+                }
+            }
             return Some(Msg::KeyPressed(key));
         });
-        return iced::Subscription::batch(vec![tick, key]);
+        let wheel = event::listen_with(|event, _, _| match event {
+            iced::Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                return Some(Msg::ScrollLines(wheel_lines(delta)));
+            }
+            _ => return None,
+        });
+        // the size the window opens at counts as a resize, as no resize event follows it
+        let resize = event::listen_with(|event, _, _| match event {
+            iced::Event::Window(
+                window::Event::Opened { size, .. } | window::Event::Resized(size),
+            ) => {
+                return Some(Msg::Resized(size));
+            }
+            _ => return None,
+        });
+        return iced::Subscription::batch(vec![tick, key, wheel, resize]);
+    }
+
+    /// title logic for model
+    #[inline]
+    pub fn title(&self) -> String {
+        return self.title.clone();
     }
 
     /// theme logic for model
@@ -202,19 +379,29 @@ impl Model {
     #[inline]
     #[expect(
         clippy::arithmetic_side_effects,
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
         clippy::wildcard_enum_match_arm,
-        reason = "bounds checked"
+        reason = "bounds checked, and wheel deltas are small"
     )]
     pub fn update(&mut self, msg: Msg) -> Task<Msg> {
         match msg {
             Msg::Exit => return window::get_latest().and_then(window::close),
+            // keyboard action mode locks the keyboard until it is reset
+            Msg::KeyPressed(_) if self.modes.get(Mode::KeyboardAction) => {
+                return iced::Task::none();
+            }
             Msg::KeyPressed(key) => {
+                self.scroll_offset = 0;
                 match key {
                     keyboard::Key::Character(chr) => match chr.chars().nth(0) {
                         Some(chr) => self.input_char(chr),
                         None => return window::get_latest().and_then(window::close),
                     },
                     keyboard::Key::Named(keyboard::key::Named::Enter) => {
+                        if self.modes.get(Mode::LineFeedNewLine) {
+                            self.input.push('\r');
+                        }
                         self.input.push('\n');
                         let write_buffer = self.input.as_bytes().to_vec();
                         if let Some(fd) = &self.fd {
@@ -253,6 +440,31 @@ impl Model {
                 }
                 return iced::Task::none();
             }
+            Msg::ScrollLines(lines) => {
+                // touchpads scroll by less than a line at a time, so the fraction carries over
+                let lines = self.scroll_remainder + lines;
+                let whole = lines.trunc();
+                self.scroll_remainder = lines - whole;
+                self.scroll_viewport(whole as isize);
+                return iced::Task::none();
+            }
+            Msg::ScrollPages(pages) => {
+                let page = isize::try_from(self.dimensions.0).unwrap_or(isize::MAX);
+                self.scroll_viewport(pages.saturating_mul(page));
+                return iced::Task::none();
+            }
+            Msg::Resized(size) => {
+                let dimensions = dimensions_for(size);
+                if dimensions != self.dimensions {
+                    self.resize(dimensions.0, dimensions.1);
+                    if let Some(fd) = &self.fd {
+                        if let Err(error) = set_pty_size(fd, dimensions) {
+                            print_err(&error);
+                        }
+                    }
+                }
+                return iced::Task::none();
+            }
             Msg::Tick => {
                 let red = read_from_option_fd(self.fd.as_ref());
                 match red {
@@ -263,6 +475,20 @@ impl Model {
                     }
                     Err(error) => print_vomit(&error.to_string()),
                 }
+                if self
+                    .flash_until
+                    .is_some_and(|until| return core_time::Instant::now() >= until)
+                {
+                    self.flash_until = None;
+                }
+                if mem::take(&mut self.attention_requested) {
+                    return window::get_latest().and_then(|id| {
+                        return window::request_user_attention(
+                            id,
+                            Some(window::UserAttention::Informational),
+                        );
+                    });
+                }
                 return iced::Task::none();
             }
         }
@@ -295,72 +521,918 @@ impl Model {
         }
     */
     fn update_screen(&mut self, vec: Vec<u8>) -> Result<(), Error> {
-        for chr in String::from_utf8_lossy(&vec).ansi_parse() {
+        if !vec.is_empty() {
+            self.scroll_offset = 0;
+        }
+        let mut parser = mem::take(&mut self.parser);
+        for chr in parser.feed(&vec) {
             match chr {
                 Token::Text(chr) => {
                     print_debug(&(String::from("[CHR]") + chr));
-                    if self.cursor.1 < self.dimensions.1 {
-                        self.cursor.1 += 1;
-                    } else {
-                        if self.cursor.0 < self.dimensions.0 {
-                            self.cursor.0 += 1;
-                            self.cursor.1 = 1;
-                        } else {
-                            self.screen.remove(0);
-                            self.cursor.1 = 1;
-                        }
-                    }
-                    let res = self.write_chr_to_screen(chr);
+                    let chr = self.translate(chr);
+                    self.print(&chr);
                 }
                 Token::C0(c0) => {
                     print_debug(&(String::from("[C0]") + &format!("{:?}", c0)));
                     match c0 {
                         C0::SP => {
-                            if self.cursor.1 < self.dimensions.1 {
-                                self.cursor.1 += 1;
-                            } else {
-                                self.cursor.0 += 1;
-                                self.cursor.1 = 1;
-                            }
-                            let res = self.write_chr_to_screen(" ");
+                            // a space is a graphic character too, using up any single shift
+                            let chr = self.translate(" ");
+                            self.print(&chr);
+                        }
+                        C0::BS => {
+                            self.pending_wrap = false;
+                            self.cursor.1 = self.cursor.1.saturating_sub(1);
                         }
-                        C0::CR => self.cursor.1 = 1,
-                        C0::LF => {
-                            if self.cursor.0 < self.dimensions.0 {
-                                self.cursor.0 += 1;
-                            } else {
-                                self.screen.remove(0);
+                        C0::CR => {
+                            self.pending_wrap = false;
+                            self.cursor.1 = self.line_start();
+                        }
+                        C0::LF | C0::VT | C0::FF => {
+                            self.pending_wrap = false;
+                            // in line feed/new line mode a line feed also returns the carriage
+                            if self.modes.get(Mode::LineFeedNewLine) {
+                                self.cursor.1 = 0;
                             }
+                            self.index();
+                        }
+                        C0::HT => {
+                            self.pending_wrap = false;
+                            self.tab_forward(1);
                         }
+                        C0::BEL => self.ring_bell(),
+                        C0::SO => self.shift = 1,
+                        C0::SI => self.shift = 0,
                         _ => (),
                     }
                 }
                 Token::EscapeSequence(seq) => {
-                    print_debug(&(String::from("[SEQ]") + &format!("{:?}", seq)))
+                    print_debug(&(String::from("[SEQ]") + &format!("{:?}", seq)));
+                    self.apply_escape_sequence(seq);
+                }
+                Token::ControlString(control_string) => {
+                    print_debug(&(String::from("[STR]") + &format!("{:?}", control_string)))
+                }
+                Token::OperatingSystemCommand(osc) => {
+                    print_debug(&(String::from("[OSC]") + &format!("{:?}", osc)));
+                    match osc {
+                        OperatingSystemCommand::SetIconNameAndWindowTitle(title)
+                        | OperatingSystemCommand::SetWindowTitle(title) => self.title = title,
+                        _ => (),
+                    }
                 }
             }
         }
+        self.parser = parser;
         return Ok(());
     }
 
-    fn write_chr_to_screen(&mut self, chr: &str) -> Result<(), Error> {
-        if self.dimensions.0 >= self.cursor.0 && self.dimensions.1 >= self.cursor.1 {
-            while self.screen.len() < self.cursor.0 {
-                self.screen.push(vec![]);
+    /// applies the effects of an escape sequence
+    #[expect(
+        clippy::arithmetic_side_effects,
+        reason = "at_least_one never returns 0"
+    )]
+    fn apply_escape_sequence(&mut self, seq: EscapeSequence) {
+        // anything but a change of pen ends a pending wrap
+        if !matches!(seq, EscapeSequence::SGR(_)) {
+            self.pending_wrap = false;
+        }
+        match seq {
+            EscapeSequence::CUU(count) => {
+                // the cursor stops at the top margin unless it started above it
+                let top = if self.cursor.0 >= self.margins.0 {
+                    self.margins.0
+                } else {
+                    0
+                };
+                let row = self.cursor.0.saturating_sub(at_least_one(count)).max(top);
+                self.move_cursor_to(row, self.cursor.1);
+            }
+            EscapeSequence::CUD(count) | EscapeSequence::VPR(count) => {
+                // the cursor stops at the bottom margin unless it started below it
+                let bottom = if self.cursor.0 <= self.margins.1 {
+                    self.margins.1
+                } else {
+                    self.dimensions.0
+                };
+                let row = self
+                    .cursor
+                    .0
+                    .saturating_add(at_least_one(count))
+                    .min(bottom);
+                self.move_cursor_to(row, self.cursor.1);
+            }
+            EscapeSequence::CUF(count) | EscapeSequence::HPR(count) => {
+                self.move_cursor_to(
+                    self.cursor.0,
+                    self.cursor.1.saturating_add(at_least_one(count)),
+                );
+            }
+            EscapeSequence::CUB(count) => {
+                self.move_cursor_to(
+                    self.cursor.0,
+                    self.cursor.1.saturating_sub(at_least_one(count)),
+                );
+            }
+            EscapeSequence::CNL(count) => {
+                self.move_cursor_to(self.cursor.0.saturating_add(at_least_one(count)), 0);
+            }
+            EscapeSequence::CPL(count) => {
+                self.move_cursor_to(self.cursor.0.saturating_sub(at_least_one(count)), 0);
+            }
+            EscapeSequence::CHA(column) | EscapeSequence::HPA(column) => {
+                let column = self.addressed_column(at_least_one(column) - 1);
+                self.move_cursor_to(self.cursor.0, column);
+            }
+            EscapeSequence::VPA(row) => {
+                let row = self.addressed_row(at_least_one(row) - 1);
+                self.move_cursor_to(row, self.cursor.1);
+            }
+            EscapeSequence::CUP(row, column) | EscapeSequence::HVP(row, column) => {
+                self.address_cursor(at_least_one(row) - 1, at_least_one(column) - 1);
+            }
+            EscapeSequence::ED(mode) => self.erase_in_display(mode),
+            EscapeSequence::EL(mode) => self.erase_in_line(mode),
+            EscapeSequence::ECH(count) => {
+                let (row, column) = self.cursor;
+                let blank = self.blank();
+                if let Some(row) = self.screen.row_mut(row) {
+                    row.clear(column..column.saturating_add(at_least_one(count)), blank);
+                }
+            }
+            EscapeSequence::ICH(count) => {
+                let (row, column) = self.cursor;
+                let (columns, blank) = (self.column_region(), self.blank());
+                if columns.contains(&column) {
+                    if let Some(row) = self.screen.row_mut(row) {
+                        row.insert_cells(column..columns.end, at_least_one(count), blank);
+                    }
+                }
+            }
+            EscapeSequence::DCH(count) => {
+                let (row, column) = self.cursor;
+                let (columns, blank) = (self.column_region(), self.blank());
+                if columns.contains(&column) {
+                    if let Some(row) = self.screen.row_mut(row) {
+                        row.delete_cells(column..columns.end, at_least_one(count), blank);
+                    }
+                }
+            }
+            EscapeSequence::IL(count) => {
+                let (region, columns) = (self.scroll_region(), self.column_region());
+                if region.contains(&self.cursor.0) && columns.contains(&self.cursor.1) {
+                    let rows = self.cursor.0..region.end;
+                    self.scroll_down_within(rows, at_least_one(count));
+                    self.cursor.1 = columns.start;
+                }
+            }
+            EscapeSequence::DL(count) => {
+                let (region, columns) = (self.scroll_region(), self.column_region());
+                if region.contains(&self.cursor.0) && columns.contains(&self.cursor.1) {
+                    let rows = self.cursor.0..region.end;
+                    self.scroll_up_within(rows, at_least_one(count));
+                    self.cursor.1 = columns.start;
+                }
+            }
+            EscapeSequence::SU(count) => self.scroll_up(at_least_one(count)),
+            EscapeSequence::SD(count) => {
+                self.scroll_down_within(self.scroll_region(), at_least_one(count));
+            }
+            EscapeSequence::SL(count) | EscapeSequence::SR(count) => {
+                let (columns, blank) = (self.column_region(), self.blank());
+                for row in self.scroll_region() {
+                    if let Some(row) = self.screen.row_mut(row) {
+                        if matches!(seq, EscapeSequence::SL(_)) {
+                            row.delete_cells(columns.clone(), at_least_one(count), blank.clone());
+                        } else {
+                            row.insert_cells(columns.clone(), at_least_one(count), blank.clone());
+                        }
+                    }
+                }
+            }
+            EscapeSequence::DECSTRM(left, right) => {
+                // without DECLRMM this is SCOSC, which saves the cursor
+                if !self.modes.get(Mode::LeftRightMargins) {
+                    self.save_cursor();
+                    return;
+                }
+                let right = if right == 0 {
+                    self.dimensions.1
+                } else {
+                    at_least_one(right).min(self.dimensions.1)
+                };
+                let left = at_least_one(left) - 1;
+                // the region must be at least two columns
+                if left + 1 < right {
+                    self.column_margins = (left, right - 1);
+                    self.address_cursor(0, 0);
+                }
+            }
+            EscapeSequence::DECSTBM(top, bottom) => {
+                let bottom = if bottom == 0 {
+                    self.dimensions.0
+                } else {
+                    at_least_one(bottom).min(self.dimensions.0)
+                };
+                let top = at_least_one(top) - 1;
+                // the region must be at least two lines
+                if top + 1 < bottom {
+                    self.margins = (top, bottom - 1);
+                    self.address_cursor(0, 0);
+                }
+            }
+            EscapeSequence::REP(count) => {
+                if let Some(cell) = self.last_printed.clone() {
+                    let mut grapheme = String::new();
+                    cell.push_to(&mut grapheme);
+                    let style = mem::replace(&mut self.style, cell.style);
+                    // repeating more than a screenful only scrolls it away, so whole lines beyond
+                    // one are dropped, which leaves the cursor where it would have ended up
+                    let per_line =
+                        (self.line_bounds().len() / usize::from(cell.width.max(1))).max(1);
+                    let screenful = self.dimensions.0 * per_line;
+                    let count = at_least_one(count);
+                    let dropped = count.saturating_sub(screenful) / per_line * per_line;
+                    for _ in 0..count - dropped {
+                        self.print(&grapheme);
+                    }
+                    self.style = style;
+                }
+            }
+            EscapeSequence::SGR(attributes) => {
+                for attribute in attributes {
+                    self.style.apply(attribute);
+                }
+            }
+            EscapeSequence::SM(modes) => {
+                for mode in modes.into_iter().filter_map(Mode::ansi) {
+                    self.set_mode(mode, true);
+                }
+            }
+            EscapeSequence::RM(modes) => {
+                for mode in modes.into_iter().filter_map(Mode::ansi) {
+                    self.set_mode(mode, false);
+                }
+            }
+            EscapeSequence::DECSET(modes) => {
+                for mode in modes.into_iter().filter_map(Mode::dec) {
+                    self.set_mode(mode, true);
+                }
+            }
+            EscapeSequence::DECRST(modes) => {
+                for mode in modes.into_iter().filter_map(Mode::dec) {
+                    self.set_mode(mode, false);
+                }
+            }
+            // a VT220 with ANSI color
+            EscapeSequence::DA => self.reply("\x1b[?62;22c"),
+            // a VT220, then the version as major, minor and patch in two digits each
+            EscapeSequence::DA2 => self.reply(&format!("\x1b[>1;{};0c", version_number())),
+            // the unit ID: "rust" in hex
+            EscapeSequence::DA3 => self.reply("\x1bP!|72757374\x1b\\"),
+            EscapeSequence::XTVERSION => self.reply(&format!(
+                "\x1bP>|{}({})\x1b\\",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            )),
+            EscapeSequence::DSR(5) => self.reply("\x1b[0n"),
+            EscapeSequence::DSR(6) => {
+                // in origin mode the position is relative to the margins
+                let (mut row, mut column) = self.cursor;
+                if self.modes.get(Mode::Origin) {
+                    row = row.saturating_sub(self.margins.0);
+                    column = column.saturating_sub(self.column_margins.0);
+                }
+                self.reply(&format!("\x1b[{};{}R", row + 1, column + 1));
+            }
+            EscapeSequence::RQM(mode) => {
+                let status = self.report_mode(Mode::ansi(mode));
+                self.reply(&format!("\x1b[{mode};{status}$y"));
+            }
+            EscapeSequence::DECRQM(mode) => {
+                let status = self.report_mode(Mode::dec(mode));
+                self.reply(&format!("\x1b[?{mode};{status}$y"));
+            }
+            EscapeSequence::CHT(count) => self.tab_forward(at_least_one(count)),
+            EscapeSequence::CBT(count) => self.tab_backward(at_least_one(count)),
+            EscapeSequence::HTS => {
+                if let Some(stop) = self.tab_stops.get_mut(self.cursor.1) {
+                    *stop = true;
+                }
+            }
+            EscapeSequence::TBC(mode) => match mode {
+                0 => {
+                    if let Some(stop) = self.tab_stops.get_mut(self.cursor.1) {
+                        *stop = false;
+                    }
+                }
+                3 => self.tab_stops.fill(false),
+                _ => (),
+            },
+            EscapeSequence::DECSC => self.save_cursor(),
+            EscapeSequence::DECRC => self.restore_cursor(),
+            EscapeSequence::IND => self.index(),
+            EscapeSequence::NEL => {
+                self.cursor.1 = 0;
+                self.index();
+            }
+            EscapeSequence::RI => self.reverse_index(),
+            EscapeSequence::RIS => self.reset(),
+            EscapeSequence::DECSTR => self.soft_reset(),
+            EscapeSequence::DECKPAM => self.modes.set(Mode::ApplicationKeypad, true),
+            EscapeSequence::DECKPNM => self.modes.set(Mode::ApplicationKeypad, false),
+            EscapeSequence::SCS(slot, charset) => {
+                if let Some(designated) = self.charsets.get_mut(slot) {
+                    *designated = charset;
+                }
+            }
+            EscapeSequence::LS2 => self.shift = 2,
+            EscapeSequence::LS3 => self.shift = 3,
+            EscapeSequence::SS2 => self.single_shift = Some(2),
+            EscapeSequence::SS3 => self.single_shift = Some(3),
+            EscapeSequence::DECALN => {
+                // clearing also marks every row unwrapped
+                self.screen.clear(Cell::new("E", 1, Style::default()));
+                self.margins = (0, self.dimensions.0.saturating_sub(1));
+                self.column_margins = (0, self.dimensions.1.saturating_sub(1));
+                self.move_cursor_to(0, 0);
+            }
+            _ => (),
+        }
+    }
+
+    /// erases part of the screen, as ED:\
+    /// 0 from the cursor to the end, 1 from the start to the cursor, 2 all of it, 3 the scrollback
+    fn erase_in_display(&mut self, mode: u32) {
+        let blank = self.blank();
+        match mode {
+            0 => {
+                self.erase_in_line(0);
+                self.screen
+                    .clear_rows(self.cursor.0 + 1..self.dimensions.0, blank);
+            }
+            1 => {
+                self.screen.clear_rows(0..self.cursor.0, blank);
+                self.erase_in_line(1);
+            }
+            2 => self.screen.clear(blank),
+            3 => {
+                // the viewport cannot stay scrolled back into history that is gone
+                self.scrollback.clear();
+                self.scroll_offset = 0;
+            }
+            _ => (),
+        }
+    }
+
+    /// erases part of the cursor's line, as EL:\
+    /// 0 from the cursor to the end, 1 from the start to the cursor, 2 all of it
+    fn erase_in_line(&mut self, mode: u32) {
+        let (row, column) = self.cursor;
+        let columns = match mode {
+            0 => column..self.dimensions.1,
+            1 => 0..column + 1,
+            2 => 0..self.dimensions.1,
+            _ => return,
+        };
+        let blank = self.blank();
+        if let Some(row) = self.screen.row_mut(row) {
+            row.clear(columns, blank);
+        }
+    }
+
+    /// sets or resets a mode, as SM, RM, DECSET and DECRST, applying its side effects
+    fn set_mode(&mut self, mode: Mode, set: bool) {
+        // the alternate screen modes are reported from `alternate_screen`, and saving the cursor
+        // is an action rather than a state, so none of them is stored
+        if !matches!(
+            mode,
+            Mode::AlternateScreen
+                | Mode::AlternateScreenClear
+                | Mode::SaveCursor
+                | Mode::AlternateScreenSaveCursor
+        ) {
+            self.modes.set(mode, set);
+        }
+        match mode {
+            Mode::Origin => self.address_cursor(0, 0),
+            Mode::LeftRightMargins => {
+                if !set {
+                    self.column_margins = (0, self.dimensions.1.saturating_sub(1));
+                }
+            }
+            Mode::Autowrap => self.pending_wrap = false,
+            Mode::AlternateScreen => self.switch_screen(set),
+            Mode::AlternateScreenClear => {
+                if set {
+                    self.switch_screen(true);
+                } else if self.alternate_screen {
+                    self.screen.clear(Cell::default());
+                    self.switch_screen(false);
+                }
+            }
+            Mode::SaveCursor => {
+                if set {
+                    self.save_cursor();
+                } else {
+                    self.restore_cursor();
+                }
+            }
+            Mode::AlternateScreenSaveCursor => {
+                if set && !self.alternate_screen {
+                    self.save_cursor();
+                    self.switch_screen(true);
+                    self.screen.clear(self.blank());
+                } else if !set && self.alternate_screen {
+                    self.switch_screen(false);
+                    self.restore_cursor();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// the DECRQM status of `mode`, as `Modes::report`, except that every alternate screen mode
+    /// reports whether the alternate screen is shown
+    fn report_mode(&self, mode: Option<Mode>) -> u8 {
+        return match mode {
+            Some(
+                Mode::AlternateScreen
+                | Mode::AlternateScreenClear
+                | Mode::AlternateScreenSaveCursor,
+            ) => {
+                if self.alternate_screen {
+                    1
+                } else {
+                    2
+                }
+            }
+            _ => self.modes.report(mode),
+        };
+    }
+
+    /// writes a response to a query back to the pty
+    fn reply(&self, response: &str) {
+        print_debug(&(String::from("[REPLY]") + &format!("{:?}", response)));
+        if let Some(fd) = &self.fd {
+            if let Err(error) = write(fd.as_fd(), response.as_bytes()) {
+                print_err(&Error::Nix(NixError::Errno(error)));
+            }
+        }
+    }
+
+    /// shows the alternate screen if `alternate` is true and the primary one otherwise
+    fn switch_screen(&mut self, alternate: bool) {
+        if alternate != self.alternate_screen {
+            mem::swap(&mut self.screen, &mut self.inactive_screen);
+            mem::swap(&mut self.saved_cursor, &mut self.inactive_saved_cursor);
+            if alternate {
+                self.primary_cursor = self.cursor;
+            }
+            self.alternate_screen = alternate;
+        }
+    }
+
+    /// saves the cursor, as DECSC
+    fn save_cursor(&mut self) {
+        self.saved_cursor = SavedCursor {
+            cursor: self.cursor,
+            style: self.style,
+            origin: self.modes.get(Mode::Origin),
+            charsets: self.charsets,
+            shift: self.shift,
+            single_shift: self.single_shift,
+        };
+    }
+
+    /// restores the cursor saved by `save_cursor`, as DECRC
+    fn restore_cursor(&mut self) {
+        self.cursor = self.saved_cursor.cursor;
+        self.style = self.saved_cursor.style;
+        self.modes.set(Mode::Origin, self.saved_cursor.origin);
+        self.charsets = self.saved_cursor.charsets;
+        self.shift = self.saved_cursor.shift;
+        self.single_shift = self.saved_cursor.single_shift;
+    }
+
+    /// moves the viewport `lines` rows back into the scrollback, or forward if negative\
+    /// the alternate screen has no history, so it never scrolls
+    fn scroll_viewport(&mut self, lines: isize) {
+        if self.alternate_screen {
+            return;
+        }
+        self.scroll_offset = self
+            .scroll_offset
+            .saturating_add_signed(lines)
+            .min(self.scrollback.len());
+    }
+
+    /// moves the cursor forward `count` tab stops, stopping at the last column
+    fn tab_forward(&mut self, count: usize) {
+        let last = self.dimensions.1.saturating_sub(1);
+        for _ in 0..count {
+            self.cursor.1 = (self.cursor.1 + 1..last)
+                .find(|column| return self.tab_stops.get(*column).copied().unwrap_or(false))
+                .unwrap_or(last);
+        }
+    }
+
+    /// moves the cursor back `count` tab stops, stopping at the first column
+    fn tab_backward(&mut self, count: usize) {
+        for _ in 0..count {
+            self.cursor.1 = (1..self.cursor.1)
+                .rev()
+                .find(|column| return self.tab_stops.get(*column).copied().unwrap_or(false))
+                .unwrap_or(0);
+        }
+    }
+
+    /// the rows lines are inserted, deleted and scrolled within
+    fn scroll_region(&self) -> Range<usize> {
+        return self.margins.0..self.margins.1 + 1;
+    }
+
+    /// the columns text is inserted, deleted and scrolled within
+    fn column_region(&self) -> Range<usize> {
+        return self.column_margins.0..self.column_margins.1 + 1;
+    }
+
+    /// the first and one past the last column of the cursor's line: the left and right margins
+    /// if the cursor is between them and the whole width otherwise
+    fn line_bounds(&self) -> Range<usize> {
+        let columns = self.column_region();
+        if columns.contains(&self.cursor.1) {
+            return columns;
+        }
+        return 0..self.dimensions.1;
+    }
+
+    /// the column a carriage return goes to: the left margin unless the cursor is left of it
+    fn line_start(&self) -> usize {
+        if self.cursor.1 >= self.column_margins.0 {
+            return self.column_margins.0;
+        }
+        return 0;
+    }
+
+    /// scrolls the scroll region up by `count` lines\
+    /// lines leaving the top of a full-screen region of the primary screen go into the scrollback
+    fn scroll_up(&mut self, count: usize) {
+        let region = self.scroll_region();
+        let full_screen = region == (0..self.dimensions.0);
+        let scrolled = self.scroll_up_within(region, count);
+        if full_screen && !self.alternate_screen {
+            self.scrollback.extend(scrolled);
+        }
+    }
+
+    /// scrolls `rows` up by `count` lines between the left and right margins\
+    /// the lines scrolled off are returned if the margins span the whole width
+    fn scroll_up_within(&mut self, rows: Range<usize>, count: usize) -> Vec<Row> {
+        let (columns, blank) = (self.column_region(), self.blank());
+        if columns == (0..self.dimensions.1) {
+            return self.screen.scroll_up(rows, count, blank);
+        }
+        self.screen.scroll_rect_up(rows, columns, count, blank);
+        return Vec::new();
+    }
+
+    /// scrolls `rows` down by `count` lines between the left and right margins
+    fn scroll_down_within(&mut self, rows: Range<usize>, count: usize) {
+        let (columns, blank) = (self.column_region(), self.blank());
+        if columns == (0..self.dimensions.1) {
+            self.screen.scroll_down(rows, count, blank);
+        } else {
+            self.screen.scroll_rect_down(rows, columns, count, blank);
+        }
+    }
+
+    /// moves the cursor to zero-based `row` and `column` as addressed by CUP and HVP
+    fn address_cursor(&mut self, row: usize, column: usize) {
+        self.move_cursor_to(self.addressed_row(row), self.addressed_column(column));
+    }
+
+    /// the screen row of zero-based `row` as addressed by CUP, HVP and VPA\
+    /// in origin mode it counts from the top margin and stops at the bottom one
+    fn addressed_row(&self, row: usize) -> usize {
+        if self.modes.get(Mode::Origin) {
+            return row.saturating_add(self.margins.0).min(self.margins.1);
+        }
+        return row;
+    }
+
+    /// the screen column of zero-based `column` as addressed by CUP, HVP, CHA and HPA\
+    /// in origin mode it counts from the left margin and stops at the right one
+    fn addressed_column(&self, column: usize) -> usize {
+        if self.modes.get(Mode::Origin) {
+            return column
+                .saturating_add(self.column_margins.0)
+                .min(self.column_margins.1);
+        }
+        return column;
+    }
+
+    /// moves the cursor to zero-based `row` and `column`, clamped to the screen
+    fn move_cursor_to(&mut self, row: usize, column: usize) {
+        self.cursor = (
+            row.min(self.dimensions.0.saturating_sub(1)),
+            column.min(self.dimensions.1.saturating_sub(1)),
+        );
+    }
+
+    /// writes a grapheme cluster at the cursor and advances it\
+    /// wide glyphs take two cells and zero-width ones join the grapheme before the cursor\
+    /// writing the last column leaves the cursor there with a wrap pending, so the line only
+    /// wraps once another character follows, and only if autowrap is on
+    fn print(&mut self, grapheme: &str) {
+        let width = self.grapheme_width(grapheme);
+        if width == 0 {
+            let (row, column) = self.cursor;
+            let Some(row) = self.screen.row_mut(row) else {
+                return;
+            };
+            // the grapheme just written, skipping the spacer of a wide glyph
+            let mut left = if self.pending_wrap {
+                Some(column)
+            } else {
+                column.checked_sub(1)
+            };
+            if left
+                .and_then(|left| row.cells.get(left))
+                .is_some_and(Cell::is_spacer)
+            {
+                left = left.and_then(|left| left.checked_sub(1));
+            }
+            if let Some(cell) = left.and_then(|left| row.cells.get_mut(left)) {
+                cell.push_str(grapheme);
+            }
+            return;
+        }
+        let line = self.line_bounds();
+        // a wide glyph that does not fit in the last column wraps early
+        if self.pending_wrap
+            || (self.modes.get(Mode::Autowrap)
+                && self.cursor.1 > line.start
+                && self.cursor.1 + width > line.end)
+        {
+            self.wrap_line();
+        }
+        // without autowrap, the last columns are overwritten instead
+        let line = self.line_bounds();
+        let columns = line.end;
+        let column = self
+            .cursor
+            .1
+            .min(columns.saturating_sub(width))
+            .max(line.start);
+        let cell = Cell::new(grapheme, u8::try_from(width).unwrap_or(1), self.style);
+        self.last_printed = Some(cell.clone());
+        let (insert, blank) = (self.modes.get(Mode::Insert), self.blank());
+        if let Some(row) = self.screen.row_mut(self.cursor.0) {
+            // insertion mode pushes the rest of the line right instead of overwriting it
+            if insert {
+                row.insert_cells(column..columns, width, blank);
+            }
+            row.write(column, cell);
+        }
+        if column + width >= columns {
+            self.cursor.1 = columns.saturating_sub(1);
+            self.pending_wrap = self.modes.get(Mode::Autowrap);
+        } else {
+            self.cursor.1 = column + width;
+        }
+    }
+
+    /// moves to the start of the next line, marking the cursor's row as soft-wrapped if the
+    /// line spans the whole width
+    fn wrap_line(&mut self) {
+        let line = self.line_bounds();
+        if line == (0..self.dimensions.1) {
+            if let Some(row) = self.screen.row_mut(self.cursor.0) {
+                row.wrapped = true;
+            }
+        }
+        self.pending_wrap = false;
+        self.cursor.1 = line.start;
+        self.index();
+    }
+
+    /// maps a single character grapheme through the character set invoked into GL, or the one
+    /// a single shift selected for it
+    fn translate<'a>(&mut self, grapheme: &'a str) -> Cow<'a, str> {
+        let slot = self.single_shift.take().unwrap_or(self.shift);
+        let charset = self.charsets.get(slot).copied().unwrap_or_default();
+        let mut chars = grapheme.chars();
+        if let (Some(chr), None) = (chars.next(), chars.next()) {
+            let mapped = charset.map(chr);
+            if mapped != chr {
+                return Cow::Owned(String::from(mapped));
+            }
+        }
+        return Cow::Borrowed(grapheme);
+    }
+
+    /// columns taken by a grapheme cluster: 0, 1 or 2
+    fn grapheme_width(&self, grapheme: &str) -> usize {
+        let width = if self.ambiguous_wide {
+            grapheme.width_cjk()
+        } else {
+            grapheme.width()
+        };
+        return width.min(2);
+    }
+
+    /// the cell erased and scrolled in cells are filled with
+    fn blank(&self) -> Cell {
+        return Cell::blank(self.style.blank());
+    }
+
+    /// moves the cursor down a line, scrolling the scroll region up at its bottom margin
+    fn index(&mut self) {
+        if self.cursor.0 == self.margins.1 {
+            // outside the left and right margins nothing scrolls
+            if self.column_region().contains(&self.cursor.1) {
+                self.scroll_up(1);
             }
-            while self.screen[self.cursor.0 - 1].len() < self.cursor.1 {
-                self.screen[self.cursor.0 - 1].push("_".to_string());
+        } else if self.cursor.0 + 1 < self.dimensions.0 {
+            self.cursor.0 += 1;
+        }
+    }
+
+    /// moves the cursor up a line, scrolling the scroll region down at its top margin
+    fn reverse_index(&mut self) {
+        if self.cursor.0 == self.margins.0 {
+            if self.column_region().contains(&self.cursor.1) {
+                self.scroll_down_within(self.scroll_region(), 1);
             }
-            self.screen[self.cursor.0 - 1].remove(self.cursor.1 - 1);
-            self.screen[self.cursor.0 - 1].insert(self.cursor.1 - 1, chr.to_string());
+        } else if self.cursor.0 > 0 {
+            self.cursor.0 -= 1;
+        }
+    }
+
+    /// resizes both screens to `rows` and `columns`\
+    /// the primary screen keeps its content whether or not it is shown: when the width changes it
+    /// is reflowed along with the history, and otherwise the lines above its cursor scroll into
+    /// the history when the cursor would fall off the bottom\
+    /// the alternate screen has no history, so it is cut to size
+    fn resize(&mut self, rows: usize, columns: usize) {
+        let rows = rows.max(1);
+        let columns = columns.max(1);
+        let (mut primary, cursor, saved) = if self.alternate_screen {
+            (
+                mem::replace(&mut self.inactive_screen, Grid::new(0, 0)),
+                self.primary_cursor,
+                self.inactive_saved_cursor.cursor,
+            )
+        } else {
+            (
+                mem::replace(&mut self.screen, Grid::new(0, 0)),
+                self.cursor,
+                self.saved_cursor.cursor,
+            )
+        };
+        let mut moved = cursor;
+        if columns == self.dimensions.1 {
+            let overflow = (cursor.0 + 1).saturating_sub(rows);
+            let scrolled = primary.scroll_up(0..self.dimensions.0, overflow, Cell::default());
+            self.scrollback.extend(scrolled);
+            moved.0 -= overflow;
+            primary.resize(rows, columns);
+        } else {
+            (primary, moved) = self.reflow(primary, cursor, rows, columns);
+        }
+        // a cursor saved where the cursor is, as by 1049, moves along with it
+        let saved = if saved == cursor { moved } else { saved };
+        if self.alternate_screen {
+            self.inactive_screen = primary;
+            self.primary_cursor = moved;
+            self.inactive_saved_cursor.cursor = saved;
+            self.screen.resize(rows, columns);
         } else {
-            return Err(Error::IndexOutOfBounds);
+            self.screen = primary;
+            self.cursor = moved;
+            self.saved_cursor.cursor = saved;
+            self.inactive_screen.resize(rows, columns);
+        }
+        self.dimensions = (rows, columns);
+        self.margins = (0, rows - 1);
+        self.column_margins = (0, columns - 1);
+        self.tab_stops = default_tab_stops(columns);
+        self.pending_wrap = false;
+        self.scroll_offset = 0;
+        self.move_cursor_to(self.cursor.0, self.cursor.1);
+        for cursor in [
+            &mut self.primary_cursor,
+            &mut self.saved_cursor.cursor,
+            &mut self.inactive_saved_cursor.cursor,
+        ] {
+            *cursor = (cursor.0.min(rows - 1), cursor.1.min(columns - 1));
+        }
+    }
+
+    /// re-wraps the history and the primary screen `screen` to `columns`, keeping `cursor` on
+    /// the cell it was on\
+    /// returns the last `rows` rows, those above them going into the history, and the cursor,
+    /// which stays in the top row if its own went into the history
+    fn reflow(
+        &mut self,
+        screen: Grid,
+        cursor: (usize, usize),
+        rows: usize,
+        columns: usize,
+    ) -> (Grid, (usize, usize)) {
+        let history = self.scrollback.len();
+        let mut lines: Vec<Row> = self.scrollback.drain().collect();
+        let mut screen = screen.into_rows();
+        // blank rows below the cursor are not part of the output
+        while screen.len() > cursor.0 + 1 && screen.last().is_some_and(Row::is_blank) {
+            screen.pop();
         }
-        Ok(())
+        lines.extend(screen);
+        let (mut lines, cursor) = reflow(lines, columns, (history + cursor.0, cursor.1));
+        let top = lines.len().saturating_sub(rows);
+        self.scrollback.extend(lines.drain(..top));
+        let mut screen = Grid::from_rows(lines, columns);
+        screen.resize(rows, columns);
+        return (screen, (cursor.0.saturating_sub(top), cursor.1));
+    }
+
+    /// returns the terminal to its initial state, as RIS
+    fn reset(&mut self) {
+        self.screen = Grid::new(self.dimensions.0, self.dimensions.1);
+        self.inactive_screen = Grid::new(self.dimensions.0, self.dimensions.1);
+        self.alternate_screen = false;
+        self.scrollback.clear();
+        self.scroll_offset = 0;
+        self.cursor = (0, 0);
+        self.primary_cursor = (0, 0);
+        self.pending_wrap = false;
+        self.modes = Modes::default();
+        self.margins = (0, self.dimensions.0.saturating_sub(1));
+        self.column_margins = (0, self.dimensions.1.saturating_sub(1));
+        self.style = Style::default();
+        self.last_printed = None;
+        self.saved_cursor = SavedCursor::default();
+        self.inactive_saved_cursor = SavedCursor::default();
+        self.tab_stops = default_tab_stops(self.dimensions.1);
+        self.charsets = [Charset::UsAscii; 4];
+        self.shift = 0;
+        self.single_shift = None;
+        self.title = String::from("rust_term");
+    }
+
+    /// flashes the screen, requests attention and runs the bell command, as configured\
+    /// bells closer together than `BELL_INTERVAL` are dropped so a flood of them cannot stall
+    /// the window
+    fn ring_bell(&mut self) {
+        let now = core_time::Instant::now();
+        if self
+            .last_bell
+            .is_some_and(|last| return now.duration_since(last) < BELL_INTERVAL)
+        {
+            return;
+        }
+        self.last_bell = Some(now);
+        if self.visual_bell {
+            self.flash_until = now.checked_add(VISUAL_BELL_DURATION);
+        }
+        if self.urgent_bell {
+            self.attention_requested = true;
+        }
+        if let Some(command) = &self.bell_command {
+            match Command::new(&self.shell).arg("-c").arg(command).spawn() {
+                // reaps the command once it exits
+                Ok(mut child) => drop(thread::spawn(move || return child.wait())),
+                Err(error) => print_err(&Error::Io(error)),
+            }
+        }
+    }
+
+    /// returns the modes, margins, pen and character sets to their initial state, as DECSTR\
+    /// unlike RIS the screen, cursor position, tab stops and title are kept
+    fn soft_reset(&mut self) {
+        for (mode, set) in [
+            (Mode::CursorVisible, true),
+            (Mode::Insert, false),
+            (Mode::Origin, false),
+            (Mode::Autowrap, false),
+            (Mode::KeyboardAction, false),
+            (Mode::ApplicationKeypad, false),
+            (Mode::CursorKeys, false),
+        ] {
+            self.modes.set(mode, set);
+        }
+        self.pending_wrap = false;
+        self.margins = (0, self.dimensions.0.saturating_sub(1));
+        self.column_margins = (0, self.dimensions.1.saturating_sub(1));
+        self.style = Style::default();
+        self.charsets = [Charset::UsAscii; 4];
+        self.shift = 0;
+        self.single_shift = None;
+        self.saved_cursor = SavedCursor::default();
     }
 
     /// view logic for model\
-    /// TODO add wide char support\
     /// TODO bound check
     #[inline]
     #[expect(
@@ -389,13 +1461,29 @@ impl Model {
                     },
                 );
         */
-        let mut body = String::new();
-        for row in &self.screen {
-            body += &row.iter().map(|a| a.to_owned()).collect::<String>();
-            body += "\n";
+        let mut palette = self.theme().palette();
+        // reverse video swaps the default colors, and the visual bell swaps them back
+        if self.modes.get(Mode::ReverseVideo) != self.flash_until.is_some() {
+            mem::swap(&mut palette.text, &mut palette.background);
+        }
+        let cursor_visible = self.modes.get(Mode::CursorVisible);
+        let mut spans = vec![];
+        // the viewport is a screenful starting `scroll_offset` rows into the history above the
+        // screen, and filled up from the top of the screen
+        let history = self.scrollback.len();
+        let top = history - self.scroll_offset.min(history);
+        let from_history = (history - top).min(self.dimensions.0);
+        for row in self.scrollback.range(top..top + from_history) {
+            push_row_spans(&mut spans, row, None, palette);
         }
-        return scrollable(column![
-            text(body),
+        let visible = self.dimensions.0 - from_history;
+        for (row_index, row) in self.screen.iter().take(visible).enumerate() {
+            let cursor = (cursor_visible && row_index == self.cursor.0).then_some(self.cursor.1);
+            push_row_spans(&mut spans, row, cursor, palette);
+        }
+        let background = palette.background;
+        return container(column![
+            rich_text(spans),
             row![
                 text(&self.input[..(self.cursor_index)]),
                 if self.cursor_index < self.input.len() {
@@ -420,28 +1508,71 @@ impl Model {
                 }
             ]
         ])
+        .style(move |_| return container::Style::default().background(background))
+        .width(Length::Fill)
+        .height(Length::Fill)
         .into();
     }
 }
 
-impl Default for Model {
-    #[inline]
+impl Model {
+    /// a model in its initial state with no pty attached
     #[expect(clippy::undocumented_unsafe_blocks, reason = "clippy be trippin")]
-    fn default() -> Self {
-        let mut me = Self {
+    fn detached() -> Self {
+        return Self {
             cursor_index: 0,
             fd: None,
             input: String::new(),
             /// SAFETY call *after* `init()`
+            parser: AnsiStreamParser::new(unsafe { MAX_STRING_LENGTH }),
+            /// SAFETY call *after* `init()`
             shell: unsafe { SHELL.clone() }.map_or_else(
                 || return String::from("/home/mtgmonkey/.nix-profile/bin/dash"),
                 |shell| return shell,
             ),
-            screen: vec![],
-            cursor: (1, 1),
+            title: String::from("rust_term"),
+            screen: Grid::new(25, 80),
+            /// SAFETY call *after* `init()`
+            scrollback: Scrollback::new(unsafe { SCROLLBACK_LINES }),
+            scroll_offset: 0,
+            scroll_remainder: 0.0,
+            inactive_screen: Grid::new(25, 80),
+            alternate_screen: false,
+            cursor: (0, 0),
+            primary_cursor: (0, 0),
+            pending_wrap: false,
+            modes: Modes::default(),
             dimensions: (25, 80),
+            margins: (0, 24),
+            column_margins: (0, 79),
+            style: Style::default(),
+            last_printed: None,
+            saved_cursor: SavedCursor::default(),
+            inactive_saved_cursor: SavedCursor::default(),
+            tab_stops: default_tab_stops(80),
+            charsets: [Charset::UsAscii; 4],
+            shift: 0,
+            single_shift: None,
+            /// SAFETY call *after* `init()`
+            ambiguous_wide: unsafe { AMBIGUOUS_WIDE },
+            /// SAFETY call *after* `init()`
+            visual_bell: unsafe { VISUAL_BELL },
+            /// SAFETY call *after* `init()`
+            urgent_bell: unsafe { URGENT_BELL },
+            /// SAFETY call *after* `init()`
+            bell_command: unsafe { BELL_COMMAND.clone() },
+            last_bell: None,
+            flash_until: None,
+            attention_requested: false,
         };
-        me.fd = spawn_pty_with_shell(&me.shell).ok();
+    }
+}
+
+impl Default for Model {
+    #[inline]
+    fn default() -> Self {
+        let mut me = Self::detached();
+        me.fd = spawn_pty_with_shell(&me.shell, me.dimensions).ok();
         let mut nored = true;
         while nored {
             let red = read_from_option_fd(me.fd.as_ref());
@@ -456,6 +1587,190 @@ impl Default for Model {
     }
 }
 
+/// rows and columns of the grid fitting in a window of `size`, leaving a line for the input
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "window sizes are small and positive"
+)]
+fn dimensions_for(size: iced::Size) -> (usize, usize) {
+    let cell = cell_size();
+    let rows = (size.height / cell.height.max(1.0)).floor() as usize;
+    let columns = (size.width / cell.width.max(1.0)).floor() as usize;
+    return (rows.saturating_sub(1).max(1), columns.max(1));
+}
+
+/// width and height of a cell, measured by laying out a glyph in the monospace font `main`
+/// sets as the default, at the default text size and line height the view renders with
+fn cell_size() -> iced::Size {
+    let paragraph = graphics::text::Paragraph::with_text(advanced::Text {
+        content: "M",
+        bounds: iced::Size::INFINITY,
+        size: iced::Settings::default().default_text_size,
+        line_height: text::LineHeight::default(),
+        font: iced::Font::MONOSPACE,
+        horizontal_alignment: alignment::Horizontal::Left,
+        vertical_alignment: alignment::Vertical::Top,
+        shaping: text::Shaping::Basic,
+        wrapping: text::Wrapping::None,
+    });
+    return paragraph.min_bounds();
+}
+
+/// lines of a mouse wheel movement, positive when scrolling back into the history
+fn wheel_lines(delta: mouse::ScrollDelta) -> f32 {
+    /// lines scrolled per notch of the wheel
+    const LINES_PER_NOTCH: f32 = 3.0;
+    /// pixels per line for devices reporting precise deltas
+    const PIXELS_PER_LINE: f32 = 20.0;
+    return match delta {
+        mouse::ScrollDelta::Lines { y, .. } => y * LINES_PER_NOTCH,
+        mouse::ScrollDelta::Pixels { y, .. } => y / PIXELS_PER_LINE,
+    };
+}
+
+/// the crate version as reported by DA2: major, minor and patch as two digits each
+fn version_number() -> u32 {
+    let part = |part: &str| return part.parse::<u32>().unwrap_or(0);
+    return part(env!("CARGO_PKG_VERSION_MAJOR")) * 10_000
+        + part(env!("CARGO_PKG_VERSION_MINOR")) * 100
+        + part(env!("CARGO_PKG_VERSION_PATCH"));
+}
+
+/// a tab stop every 8 columns
+fn default_tab_stops(columns: usize) -> Vec<bool> {
+    return (0..columns)
+        .map(|column| return column > 0 && column % 8 == 0)
+        .collect();
+}
+
+/// a count or one-based position parameter, where ECMA-48 treats 0 as 1
+fn at_least_one(param: u32) -> usize {
+    return usize::try_from(param.max(1)).unwrap_or(usize::MAX);
+}
+
+/// appends the spans drawing `row`, with the cursor drawn in column `cursor` if it is given
+fn push_row_spans(
+    spans: &mut Vec<text::Span<'static, Msg>>,
+    row: &Row,
+    cursor: Option<usize>,
+    palette: theme::Palette,
+) {
+    // consecutive cells of the same style share a span
+    let mut run = String::new();
+    let mut run_style = None;
+    for (column_index, cell) in row.cells.iter().enumerate() {
+        // the right half of a wide glyph is drawn by the cell to its left
+        if cell.is_spacer() {
+            continue;
+        }
+        let is_cursor = cursor == Some(column_index)
+            || (cell.width > 1 && cursor == Some(column_index.saturating_add(1)));
+        let style = (cell.style, is_cursor);
+        if run_style != Some(style) {
+            if let Some((style, is_cursor)) = run_style {
+                spans.push(styled_span(mem::take(&mut run), style, is_cursor, palette));
+            }
+            run_style = Some(style);
+        }
+        cell.push_to(&mut run);
+    }
+    if let Some((style, is_cursor)) = run_style {
+        spans.push(styled_span(run, style, is_cursor, palette));
+    }
+    spans.push(span("\n"));
+}
+
+/// a span of `txt` drawn in `style`, inverted if it is under the cursor
+fn styled_span(
+    txt: String,
+    style: Style,
+    is_cursor: bool,
+    palette: theme::Palette,
+) -> text::Span<'static, Msg> {
+    let mut foreground = resolve_color(style.foreground, palette.text);
+    let mut background = resolve_color(style.background, palette.background);
+    let inverse = style.attributes.contains(Attributes::INVERSE);
+    if inverse != is_cursor {
+        mem::swap(&mut foreground, &mut background);
+    }
+    if style.attributes.contains(Attributes::FAINT) {
+        foreground = foreground.scale_alpha(0.5);
+    }
+    if style.attributes.contains(Attributes::CONCEAL) {
+        foreground = background;
+    }
+    let font = iced::Font {
+        weight: if style.attributes.contains(Attributes::BOLD) {
+            font::Weight::Bold
+        } else {
+            font::Weight::Normal
+        },
+        style: if style.attributes.contains(Attributes::ITALIC) {
+            font::Style::Italic
+        } else {
+            font::Style::Normal
+        },
+        ..iced::Font::MONOSPACE
+    };
+    let has_background = inverse || is_cursor || style.background != Color::Default;
+    return span(txt)
+        .color(foreground)
+        .background_maybe(has_background.then_some(background))
+        .font(font)
+        .underline(style.underline.is_some())
+        .strikethrough(style.attributes.contains(Attributes::CROSSED_OUT));
+}
+
+/// the `iced::Color` of a SGR color, with `default` standing in for `Color::Default`
+fn resolve_color(color: Color, default: iced::Color) -> iced::Color {
+    match color {
+        Color::Default => return default,
+        Color::Rgb(red, green, blue) => return iced::Color::from_rgb8(red, green, blue),
+        Color::Indexed(index) => return indexed_color(index),
+    }
+}
+
+/// the 256 color palette: 16 gruvbox colors, a 6x6x6 color cube and a 24 step grey ramp
+#[expect(clippy::arithmetic_side_effects, reason = "bounded by the match arms")]
+fn indexed_color(index: u8) -> iced::Color {
+    /// gruvbox dark, to match `Model::theme`
+    const ANSI: [(u8, u8, u8); 16] = [
+        (0x28, 0x28, 0x28),
+        (0xcc, 0x24, 0x1d),
+        (0x98, 0x97, 0x1a),
+        (0xd7, 0x99, 0x21),
+        (0x45, 0x85, 0x88),
+        (0xb1, 0x62, 0x86),
+        (0x68, 0x9d, 0x6a),
+        (0xa8, 0x99, 0x84),
+        (0x92, 0x83, 0x74),
+        (0xfb, 0x49, 0x34),
+        (0xb8, 0xbb, 0x26),
+        (0xfa, 0xbd, 0x2f),
+        (0x83, 0xa5, 0x98),
+        (0xd3, 0x86, 0x9b),
+        (0x8e, 0xc0, 0x7c),
+        (0xeb, 0xdb, 0xb2),
+    ];
+    let level = |step: u8| if step == 0 { 0 } else { 55 + 40 * step };
+    match index {
+        0..=15 => {
+            let (red, green, blue) = ANSI[usize::from(index)];
+            return iced::Color::from_rgb8(red, green, blue);
+        }
+        16..=231 => {
+            let cube = index - 16;
+            return iced::Color::from_rgb8(level(cube / 36), level(cube / 6 % 6), level(cube % 6));
+        }
+        232..=255 => {
+            let grey = 8 + 10 * (index - 232);
+            return iced::Color::from_rgb8(grey, grey, grey);
+        }
+    }
+}
+
 /// # Safety
 /// call *before* creating a `Model` because `Model::default()` relies on `SHELL`
 /// call *before* `print_err()` because `print_err()` relies on `VERBOSE`
@@ -474,14 +1789,36 @@ pub unsafe fn init(flags: Flags) {
     unsafe {
         SHELL = flags.shell;
     }
+    if let Some(max_string_length) = flags.max_string_length {
+        unsafe {
+            MAX_STRING_LENGTH = max_string_length;
+        }
+    }
+    unsafe {
+        AMBIGUOUS_WIDE = flags.ambiguous_wide;
+    }
+    if let Some(scrollback_lines) = flags.scrollback_lines {
+        unsafe {
+            SCROLLBACK_LINES = scrollback_lines;
+        }
+    }
+    unsafe {
+        VISUAL_BELL = flags.visual_bell;
+    }
+    unsafe {
+        URGENT_BELL = flags.urgent_bell;
+    }
+    unsafe {
+        BELL_COMMAND = flags.bell_command;
+    }
 }
 
 /// spawns a pty with the specified shell program
 #[expect(clippy::single_call_fn, reason = "abstraction")]
-fn spawn_pty_with_shell(default_shell: &str) -> Result<OwnedFd, Error> {
+fn spawn_pty_with_shell(default_shell: &str, dimensions: (usize, usize)) -> Result<OwnedFd, Error> {
     // SAFETY: always safe unless the OS is out of ptys
     // so it is always safe
-    match unsafe { forkpty(None, None) } {
+    match unsafe { forkpty(Some(&winsize(dimensions)), None) } {
         Ok(fork_pty_res) => match fork_pty_res {
             ForkptyResult::Parent { master, .. } => {
                 if let Err(error) = set_nonblock(&master) {
@@ -501,6 +1838,32 @@ fn spawn_pty_with_shell(default_shell: &str) -> Result<OwnedFd, Error> {
     }
 }
 
+nix::ioctl_write_ptr_bad!(
+    /// sets the window size of a terminal, sending SIGWINCH to its foreground process group
+    tiocswinsz,
+    nix::libc::TIOCSWINSZ,
+    Winsize
+);
+
+/// tells the pty and so the programs running in it that the terminal is now `dimensions`
+fn set_pty_size(fd: &OwnedFd, dimensions: (usize, usize)) -> Result<(), Error> {
+    // SAFETY: `fd` is an open pty and the winsize outlives the call
+    match unsafe { tiocswinsz(fd.as_raw_fd(), &winsize(dimensions)) } {
+        Ok(_) => return Ok(()),
+        Err(errno) => return Err(Error::Nix(NixError::Errno(errno))),
+    }
+}
+
+/// the `Winsize` of a terminal of `dimensions` rows and columns
+fn winsize(dimensions: (usize, usize)) -> Winsize {
+    return Winsize {
+        ws_row: u16::try_from(dimensions.0).unwrap_or(u16::MAX),
+        ws_col: u16::try_from(dimensions.1).unwrap_or(u16::MAX),
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+}
+
 /// reads from an `&OwnedFd`
 /// TODO check bounds
 #[expect(
@@ -590,3 +1953,164 @@ fn print_debug(debug: &str) {
         println!("[DEBUG] {:?}", debug);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the text of each row of `grid`, without trailing blanks
+    fn rows(grid: &Grid) -> Vec<String> {
+        return grid
+            .iter()
+            .map(|row| {
+                let mut text = String::new();
+                for cell in &row.cells {
+                    cell.push_to(&mut text);
+                }
+                return text.trim_end().to_owned();
+            })
+            .collect();
+    }
+
+    /// a detached model of `rows` and `columns` that has been fed `input`
+    fn model_with(rows: usize, columns: usize, input: &str) -> Model {
+        let mut model = Model::detached();
+        model.resize(rows, columns);
+        model.update_screen(input.as_bytes().to_vec()).unwrap();
+        return model;
+    }
+
+    #[test]
+    fn scroll_region_scrolls_alone() {
+        let model = model_with(4, 5, "1\r\n2\r\n3\r\n4\x1b[2;3r\x1b[3;1H\n");
+        assert_eq!(rows(&model.screen), vec!["1", "3", "", "4"]);
+        assert_eq!(model.cursor, (2, 0));
+        assert!(model.scrollback.is_empty());
+    }
+
+    #[test]
+    fn tab_stops() {
+        let model = model_with(1, 20, "a\tb");
+        assert_eq!(model.cursor, (0, 9));
+        let model = model_with(1, 20, "\x1b[1;4H\x1bH\r\tx");
+        assert_eq!(model.cursor, (0, 4));
+        let model = model_with(1, 20, "\x1b[3g\tx\x1b[Zy");
+        assert_eq!(rows(&model.screen), vec!["y                  x"]);
+    }
+
+    #[test]
+    fn pending_wrap() {
+        let mut model = model_with(2, 3, "abc");
+        assert_eq!(model.cursor, (0, 2));
+        assert!(model.pending_wrap);
+        model.update_screen(b"d".to_vec()).unwrap();
+        assert_eq!(rows(&model.screen), vec!["abc", "d"]);
+        assert_eq!(model.cursor, (1, 1));
+        assert!(model.screen.iter().next().unwrap().wrapped);
+        let model = model_with(2, 3, "\x1b[?7labcde");
+        assert_eq!(rows(&model.screen), vec!["abe", ""]);
+    }
+
+    #[test]
+    fn origin_mode_addresses_within_margins() {
+        let mut model = model_with(6, 30, "\x1b[?69h\x1b[10;20s\x1b[2;5r\x1b[?6h\x1b[5G");
+        assert_eq!(model.cursor, (1, 13));
+        model.update_screen(b"\x1b[3d".to_vec()).unwrap();
+        assert_eq!(model.cursor, (3, 13));
+        model.update_screen(b"\x1b[9;99H".to_vec()).unwrap();
+        assert_eq!(model.cursor, (4, 19));
+    }
+
+    #[test]
+    fn shifting_cells_mends_wide_glyphs() {
+        let model = model_with(1, 6, "ab\u{4f60}c\x1b[1;3H\x1b[P");
+        assert_eq!(rows(&model.screen), vec!["ab c"]);
+        assert!(model.screen.iter().all(|row| {
+            return row.cells.iter().all(|cell| return !cell.is_spacer());
+        }));
+        let model = model_with(1, 4, "ab\u{4f60}\x1b[1;1H\x1b[@");
+        assert_eq!(rows(&model.screen), vec![" ab"]);
+        assert!(model.screen.iter().all(|row| {
+            return row.cells.iter().all(|cell| return cell.width == 1);
+        }));
+        let model = model_with(
+            2,
+            4,
+            "\u{4f60}ab\r\nc\u{4f60}\x1b[?69h\x1b[2;4s\x1b[2;2H\x1b[S",
+        );
+        assert_eq!(rows(&model.screen), vec![" \u{4f60}", "c"]);
+        assert_eq!(model.screen.iter().next().unwrap().cells[0].width, 1);
+    }
+
+    #[test]
+    fn each_screen_saves_its_own_cursor() {
+        let mut model = model_with(6, 10, "one\r\n$ \x1b[?1049h\x1b[5;5H\x1b7\x1b[1;1H");
+        model.update_screen(b"\x1b8".to_vec()).unwrap();
+        assert_eq!(model.cursor, (4, 4));
+        model.update_screen(b"\x1b[?1049l".to_vec()).unwrap();
+        assert_eq!(model.cursor, (1, 2));
+        assert_eq!(rows(&model.screen)[..2], ["one", "$"]);
+    }
+
+    #[test]
+    fn space_uses_up_single_shift() {
+        let model = model_with(1, 4, "\x1b*0\x1bN q");
+        assert_eq!(rows(&model.screen), vec![" q"]);
+        assert_eq!(model.single_shift, None);
+    }
+
+    #[test]
+    fn repeat_wraps_as_if_printed() {
+        for count in [35, 3005] {
+            let model = model_with(3, 10, &format!("-\x1b[{count}b"));
+            assert_eq!(model.cursor, (2, 6));
+            assert_eq!(
+                rows(&model.screen),
+                vec!["----------", "----------", "------"]
+            );
+        }
+        let model = model_with(3, 10, "\x1b[?69h\x1b[3;7s\x1b[1;3H-\x1b[1003b");
+        assert_eq!(model.cursor, (2, 6));
+        assert_eq!(rows(&model.screen), vec!["  -----", "  -----", "  ----"]);
+    }
+
+    #[test]
+    fn screen_alignment_resets_margins() {
+        let model = model_with(3, 4, "\x1b[2;3r\x1b[?69h\x1b[2;3s\x1b#8");
+        assert_eq!(rows(&model.screen), vec!["EEEE", "EEEE", "EEEE"]);
+        assert_eq!(model.cursor, (0, 0));
+        assert_eq!(model.margins, (0, 2));
+        assert_eq!(model.column_margins, (0, 3));
+    }
+
+    #[test]
+    fn resize_on_alternate_screen_keeps_newest_primary_rows() {
+        let screen = "aaaaaaaaaa\r\nbbbbbbbbbb\r\ncccccccccc\r\n$ ";
+        for (enter, leave) in [
+            ("\x1b[?47h", "\x1b[?47l"),
+            ("\x1b[?1049h\x1b[1;1H\x1b7", "\x1b[?1049l"),
+        ] {
+            let mut model = model_with(4, 10, &(String::from(screen) + enter));
+            model.resize(4, 5);
+            model.update_screen(leave.as_bytes().to_vec()).unwrap();
+            assert_eq!(rows(&model.screen), vec!["bbbbb", "ccccc", "ccccc", "$"]);
+            let history = Grid::from_rows(model.scrollback.drain().collect(), 5);
+            assert_eq!(rows(&history), vec!["aaaaa", "aaaaa", "bbbbb"]);
+        }
+    }
+
+    #[test]
+    fn resize_on_alternate_screen_reflows_primary() {
+        let mut model = Model::detached();
+        model.resize(3, 6);
+        model
+            .update_screen(b"abcdefghij\r\n$ \x1b[?1049hvim".to_vec())
+            .unwrap();
+        model.resize(3, 4);
+        model.update_screen(b"\x1b[?1049l".to_vec()).unwrap();
+        assert_eq!(rows(&model.screen), vec!["efgh", "ij", "$"]);
+        assert_eq!(model.cursor, (2, 2));
+        let history = Grid::from_rows(model.scrollback.drain().collect(), 4);
+        assert_eq!(rows(&history), vec!["abcd"]);
+    }
+}
@@ -18,7 +18,7 @@ fn main() -> iced::Result {
     unsafe {
         init(flags().run());
     };
-    return iced::application("test", Model::update, Model::view)
+    return iced::application(Model::title, Model::update, Model::view)
         .theme(Model::theme)
         .default_font(iced::Font::MONOSPACE)
         .decorations(false)
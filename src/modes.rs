@@ -0,0 +1,126 @@
+//! the mode registry: ANSI modes set by SM and RM, and DEC private modes set by DECSET and DECRST
+
+use std::collections::BTreeSet;
+
+/// a mode known to the terminal
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mode {
+    KeyboardAction,            // 2 KAM Keyboard Action Mode, locks the keyboard
+    Insert,                    // 4 IRM Insertion Replacement Mode
+    SendReceive,               // 12 SRM Send/Receive Mode, reset echoes locally
+    LineFeedNewLine,           // 20 LNM Line feed/New line Mode
+    CursorKeys,                // ?1 DECCKM Cursor Keys Mode, set sends application sequences
+    Columns132,                // ?3 DECCOLM 132 Column Mode
+    SmoothScroll,              // ?4 DECSCLM SCroLling Mode
+    ReverseVideo,              // ?5 DECSCNM SCreeN Mode, set swaps the default colors
+    Origin,                    // ?6 DECOM Origin Mode, set addresses within the margins
+    Autowrap,                  // ?7 DECAWM AutoWrap Mode
+    Autorepeat,                // ?8 DECARM Auto Repeat Mode
+    X10Mouse,                  // ?9 report button presses
+    CursorBlink,               // ?12 blink the cursor
+    CursorVisible,             // ?25 DECTCEM Text Cursor Enable Mode
+    AlternateScreen,           // ?47 use the alternate screen
+    ApplicationKeypad,         // ?66 DECNKM Numeric Keypad Mode, also set by DECKPAM
+    LeftRightMargins,          // ?69 DECLRMM Left Right Margin Mode, enables DECSLRM
+    MouseClicks,               // ?1000 report button presses and releases
+    MouseDrag,                 // ?1002 also report motion while a button is held
+    MouseMotion,               // ?1003 also report all motion
+    FocusEvents,               // ?1004 report focus in and out
+    Utf8Mouse,                 // ?1005 UTF-8 encoded mouse reports
+    SgrMouse,                  // ?1006 SGR encoded mouse reports
+    AlternateScreenClear,      // ?1047 use the alternate screen, clearing it on leaving
+    SaveCursor,                // ?1048 set saves and reset restores the cursor, as DECSC and DECRC
+    AlternateScreenSaveCursor, // ?1049 1048 and 1047 together, clearing the alternate screen on entry
+    BracketedPaste,            // ?2004 bracket pasted text
+}
+
+impl Mode {
+    /// the ANSI mode numbered `number`, as used by SM, RM and DECRQM
+    #[inline]
+    pub const fn ansi(number: u32) -> Option<Self> {
+        return match number {
+            2 => Some(Self::KeyboardAction),
+            4 => Some(Self::Insert),
+            12 => Some(Self::SendReceive),
+            20 => Some(Self::LineFeedNewLine),
+            _ => None,
+        };
+    }
+
+    /// the DEC private mode numbered `number`, as used by DECSET, DECRST and DECRQM
+    #[inline]
+    pub const fn dec(number: u32) -> Option<Self> {
+        return match number {
+            1 => Some(Self::CursorKeys),
+            3 => Some(Self::Columns132),
+            4 => Some(Self::SmoothScroll),
+            5 => Some(Self::ReverseVideo),
+            6 => Some(Self::Origin),
+            7 => Some(Self::Autowrap),
+            8 => Some(Self::Autorepeat),
+            9 => Some(Self::X10Mouse),
+            12 => Some(Self::CursorBlink),
+            25 => Some(Self::CursorVisible),
+            47 => Some(Self::AlternateScreen),
+            66 => Some(Self::ApplicationKeypad),
+            69 => Some(Self::LeftRightMargins),
+            1000 => Some(Self::MouseClicks),
+            1002 => Some(Self::MouseDrag),
+            1003 => Some(Self::MouseMotion),
+            1004 => Some(Self::FocusEvents),
+            1005 => Some(Self::Utf8Mouse),
+            1006 => Some(Self::SgrMouse),
+            1047 => Some(Self::AlternateScreenClear),
+            1048 => Some(Self::SaveCursor),
+            1049 => Some(Self::AlternateScreenSaveCursor),
+            2004 => Some(Self::BracketedPaste),
+            _ => None,
+        };
+    }
+}
+
+/// which modes are set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modes(BTreeSet<Mode>);
+
+impl Modes {
+    /// whether `mode` is set
+    #[inline]
+    pub fn get(&self, mode: Mode) -> bool {
+        return self.0.contains(&mode);
+    }
+
+    /// sets `mode` if `value` is true and resets it otherwise
+    #[inline]
+    pub fn set(&mut self, mode: Mode, value: bool) {
+        if value {
+            self.0.insert(mode);
+        } else {
+            self.0.remove(&mode);
+        }
+    }
+
+    /// the DECRQM status of `mode`: 0 if it is unknown, 1 if set and 2 if reset
+    #[inline]
+    pub fn report(&self, mode: Option<Mode>) -> u8 {
+        return match mode {
+            None => 0,
+            Some(mode) if self.get(mode) => 1,
+            Some(_) => 2,
+        };
+    }
+}
+
+impl Default for Modes {
+    /// the modes set at power on
+    #[inline]
+    fn default() -> Self {
+        return Self(BTreeSet::from([
+            Mode::SendReceive,
+            Mode::Autowrap,
+            Mode::Autorepeat,
+            Mode::CursorVisible,
+        ]));
+    }
+}
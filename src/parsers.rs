@@ -3,165 +3,502 @@
 //! as parts of it are from [this](https://gitlab.com/davidbittner/ansi-parser) excellent crate
 
 use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::{digit0, digit1};
-use nom::combinator::{map, map_res, opt, value};
-use nom::sequence::{delimited, preceded};
+use nom::bytes::streaming::{tag, take_till, take_while};
+use nom::character::streaming::{anychar, one_of, satisfy};
+use nom::combinator::{map, map_opt, opt, value};
+use nom::sequence::preceded;
 use nom::{IResult, Parser};
 
-use crate::enums::{C0, EscapeSequence};
+use crate::enums::{
+    C0, Charset, Color, ControlString, Csi, EscapeSequence, OperatingSystemCommand, SgrAttribute,
+    Token, Underline,
+};
 
-macro_rules! tag_parser {
-    ($sig:ident, $tag:expr, $ret:expr) => {
-        fn $sig(input: &str) -> IResult<&str, EscapeSequence> {
-            value($ret, tag($tag)).parse(input)
-        }
-    };
+/// parses the ECMA-48 control sequence grammar following `ESC`:\
+/// `[`, an optional private marker, parameter bytes, intermediate bytes and a final byte\
+/// the parsers are streaming, so a sequence cut short by the end of input is `Err::Incomplete`
+fn csi(input: &str) -> IResult<&str, Csi> {
+    map(
+        (
+            tag("["),
+            opt(one_of("?>=<")),
+            take_while(|c: char| c.is_ascii_digit() || c == ';' || c == ':'),
+            take_while(|c: char| ('\u{20}'..='\u{2f}').contains(&c)),
+            satisfy(|c| ('\u{40}'..='\u{7e}').contains(&c)),
+        ),
+        |(_, private, params, intermediates, final_byte)| Csi {
+            private,
+            params: split_params(params),
+            intermediates: intermediates.to_owned(),
+            final_byte,
+        },
+    )
+    .parse(input)
 }
 
-macro_rules! one_ctlseq {
-    ($sig:ident, $tag:expr, $def:expr, $ret:expr) => {
-        fn $sig(input: &str) -> IResult<&str, EscapeSequence> {
-            map(
-                delimited(tag("["), |a| parse_maybe_int(a, $def), tag($tag)),
-                |am| $ret(am),
-            )
-            .parse(input)
-        }
-    };
+/// parses the escape sequences following `ESC` that are not control sequences or strings
+fn esc(input: &str) -> IResult<&str, EscapeSequence> {
+    use EscapeSequence::*;
+    alt((
+        value(DECSC, tag("7")),
+        value(DECRC, tag("8")),
+        value(IND, tag("D")),
+        value(NEL, tag("E")),
+        value(HTS, tag("H")),
+        value(RI, tag("M")),
+        value(RIS, tag("c")),
+        value(DECKPAM, tag("=")),
+        value(DECKPNM, tag(">")),
+        value(DECALN, tag("#8")),
+        value(LS2, tag("n")),
+        value(LS3, tag("o")),
+        value(SS2, tag("N")),
+        value(SS3, tag("O")),
+        map_opt((one_of("()*+"), anychar), |(slot, charset)| {
+            let slot = match slot {
+                '(' => 0,
+                ')' => 1,
+                '*' => 2,
+                _ => 3,
+            };
+            Some(SCS(slot, charset_from_final(charset)?))
+        }),
+    ))
+    .parse(input)
 }
 
-macro_rules! two_ctlseq {
-    ($sig:ident, $tag:expr, $def0:expr, $def1:expr, $ret:expr) => {
-        fn $sig(input: &str) -> IResult<&str, EscapeSequence> {
-            map(
-                (
-                    tag("["),
-                    |a| parse_maybe_int(a, $def0),
-                    opt(tag(";")),
-                    |b| parse_maybe_int(b, $def1),
-                    tag($tag),
-                ),
-                |(_, a, _, b, _)| $ret(a, b),
-            )
-            .parse(input)
-        }
-    };
+/// the character set designated by the final byte of an SCS sequence
+fn charset_from_final(final_byte: char) -> Option<Charset> {
+    use Charset::*;
+    Some(match final_byte {
+        'B' => UsAscii,
+        '0' => DecSpecialGraphics,
+        'A' => British,
+        '4' => Dutch,
+        'C' | '5' => Finnish,
+        'R' | 'f' => French,
+        'Q' | '9' => FrenchCanadian,
+        'K' => German,
+        'Y' => Italian,
+        'E' | '6' | '`' => NorwegianDanish,
+        'Z' => Spanish,
+        'H' | '7' => Swedish,
+        '=' => Swiss,
+        _ => return None,
+    })
 }
 
-fn parse_u32(input: &str) -> IResult<&str, u32> {
-    map_res(digit1, |s: &str| s.parse::<u32>()).parse(input)
+/// parses a control string following `ESC`:\
+/// `introducer`, then a payload terminated by ST (`ESC \\`), or also by BEL after OSC as xterm
+/// does\
+/// payloads longer than `max_length` bytes are truncated
+fn control_string<'a>(
+    introducer: &'static str,
+    max_length: usize,
+) -> impl Parser<&'a str, Output = &'a str, Error = nom::error::Error<&'a str>> {
+    let bel = introducer == "]";
+    map(
+        (
+            tag(introducer),
+            take_till(move |c| ends_payload(c, bel)),
+            move |input| string_terminator(input, bel),
+        ),
+        move |(_, payload, _)| truncate(payload, max_length),
+    )
 }
 
-fn parse_maybe_int(input: &str, default: u32) -> IResult<&str, u32> {
-    map(digit0, |s: &str| s.parse::<u32>().unwrap_or(default)).parse(input)
+/// parses a control string following `ESC` that CAN or SUB cut short, which discards it\
+/// the output is the C0 control that ended it
+fn aborted_string(input: &str) -> IResult<&str, C0> {
+    map(
+        (
+            one_of("]PX^_"),
+            take_till(|c| ends_payload(c, false)),
+            one_of("\u{18}\u{1a}"),
+        ),
+        |(_, _, abort)| if abort == '\u{18}' { C0::CAN } else { C0::SUB },
+    )
+    .parse(input)
 }
 
-fn combined(input: &str) -> IResult<&str, EscapeSequence> {
-    alt((
-        alt((
-            ICH, CUU, CUD, CUF, CUB, CNL, CPL, CHA, CUP, CHT, ED, EL, IL, DL, EF, EA, DCH, SEM,
-            CPR, SU, SD,
-        )),
-        alt((
-            NP,
-            PP,
-            CTC,
-            ECH,
-            CVT,
-            CBT,
-            HPA,
-            HPR,
-            REP,
-            DA,
-            VPA,
-            VPR,
-            HVP,
-            TBC,
-            SM,
-            MC,
-            PageFormatSelect,
-            RM,
-            /*SGR,*/ DSR,
-            DAQ,
-        )),
+/// whether `c` ends a control string payload: ESC beginning ST, CAN or SUB aborting it, or BEL
+/// if `bel` is true
+fn ends_payload(c: char, bel: bool) -> bool {
+    matches!(c, '\u{1b}' | '\u{18}' | '\u{1a}') || (bel && c == '\u{07}')
+}
+
+/// ST, or also BEL if `bel` is true, either of which ends a control string
+fn string_terminator(input: &str, bel: bool) -> IResult<&str, &str> {
+    if bel {
+        alt((tag("\u{07}"), tag("\u{1b}\\"))).parse(input)
+    } else {
+        tag("\u{1b}\\").parse(input)
+    }
+}
+
+/// the longest prefix of `input` that is at most `max_length` bytes and ends on a char boundary
+pub fn truncate(input: &str, max_length: usize) -> &str {
+    let mut end = max_length.min(input.len());
+    while !input.is_char_boundary(end) {
+        end -= 1;
+    }
+    &input[..end]
+}
+
+/// parses everything that may follow `ESC`\
+/// control string payloads longer than `max_string_length` bytes are truncated, and those cut
+/// short by CAN or SUB are discarded
+pub fn parse_escape(input: &str, max_string_length: usize) -> IResult<&str, Token<'static>> {
+    let string = |introducer| control_string(introducer, max_string_length);
+    let owned = |payload: &str| payload.to_owned();
+    preceded(
+        tag("\u{1b}"),
         alt((
-            DECLL, DECSTBM, DECSTRM, DECSLPP, /*DECSHTS,*/ /*DECSVTS,*/ DECSHORP,
-            /*DECREQTPARM,*/ DECTST, DECVERP, DECTTC, /*DECPRO,*/ DECKEYS,
-            /*DELETE,*/ SL, SR, GSM, GSS, FNT, TSS, JFY,
+            map(csi, |csi| Token::EscapeSequence(csi.into())),
+            map(esc, Token::EscapeSequence),
+            map(string("]"), |payload| {
+                Token::OperatingSystemCommand(payload.into())
+            }),
+            map(string("P"), |payload| {
+                Token::ControlString(ControlString::DCS(owned(payload)))
+            }),
+            map(string("X"), |payload| {
+                Token::ControlString(ControlString::SOS(owned(payload)))
+            }),
+            map(string("^"), |payload| {
+                Token::ControlString(ControlString::PM(owned(payload)))
+            }),
+            map(string("_"), |payload| {
+                Token::ControlString(ControlString::APC(owned(payload)))
+            }),
+            map(aborted_string, Token::C0),
         )),
-        SPI,
-        QUAD,
-    ))
+    )
     .parse(input)
 }
 
-pub fn parse_escape(input: &str) -> IResult<&str, EscapeSequence> {
-    preceded(tag("\u{1b}"), combined).parse(input)
+impl From<&str> for OperatingSystemCommand {
+    /// splits an OSC payload into its number and text
+    fn from(payload: &str) -> Self {
+        use OperatingSystemCommand::*;
+        match payload.split_once(';') {
+            Some(("0", text)) => SetIconNameAndWindowTitle(text.to_owned()),
+            Some(("1", text)) => SetIconName(text.to_owned()),
+            Some(("2", text)) => SetWindowTitle(text.to_owned()),
+            _ => Raw(payload.to_owned()),
+        }
+    }
+}
+
+impl From<Csi> for EscapeSequence {
+    /// the table of known control sequences, keyed by private marker, intermediates and final byte
+    fn from(csi: Csi) -> Self {
+        use EscapeSequence::*;
+        let p = |index, default| csi.param(index, default);
+        match (csi.private, csi.intermediates.as_str(), csi.final_byte) {
+            (None, "", '@') => ICH(p(0, 1)),
+            (None, "", 'A') => CUU(p(0, 1)),
+            (None, "", 'B') => CUD(p(0, 1)),
+            (None, "", 'C') => CUF(p(0, 1)),
+            (None, "", 'D') => CUB(p(0, 1)),
+            (None, "", 'E') => CNL(p(0, 1)),
+            (None, "", 'F') => CPL(p(0, 1)),
+            (None, "", 'G') => CHA(p(0, 1)),
+            (None, "", 'H') => CUP(p(0, 1), p(1, 1)),
+            (None, "", 'I') => CHT(p(0, 1)),
+            (None, "", 'J') => ED(p(0, 0)),
+            (None, "", 'K') => EL(p(0, 0)),
+            (None, "", 'L') => IL(p(0, 0)),
+            (None, "", 'M') => DL(p(0, 0)),
+            (None, "", 'N') => EF(p(0, 0)),
+            (None, "", 'O') => EA(p(0, 0)),
+            (None, "", 'P') => DCH(p(0, 1)),
+            (None, "", 'Q') => SEM(p(0, 0)),
+            (None, "", 'R') => CPR,
+            (None, "", 'S') => SU(p(0, 1)),
+            (None, "", 'T') => SD(p(0, 1)),
+            (None, "", 'U') => NP(p(0, 1)),
+            (None, "", 'V') => PP(p(0, 1)),
+            (None, "", 'W') => CTC(p(0, 0)),
+            (None, "", 'X') => ECH(p(0, 1)),
+            (None, "", 'Y') => CVT(p(0, 1)),
+            (None, "", 'Z') => CBT(p(0, 1)),
+            (None, "", '`') => HPA(p(0, 0)),
+            (None, "", 'a') => HPR(p(0, 0)),
+            (None, "", 'b') => REP(p(0, 1)),
+            (None, "", 'c') if p(0, 0) == 0 => DA,
+            (None, "", 'd') => VPA(p(0, 0)),
+            (None, "", 'e') => VPR(p(0, 0)),
+            (None, "", 'f') => HVP(p(0, 1), p(1, 1)),
+            (None, "", 'g') => TBC(p(0, 0)),
+            (None, "", 'h') => SM(csi.params_or(0)),
+            (None, "", 'i') => MC(p(0, 0)),
+            (None, "", 'j') => PageFormatSelect(p(0, 0)),
+            (None, "", 'l') => RM(csi.params_or(0)),
+            (None, "", 'm') => SGR(sgr_attributes(&csi.params)),
+            (None, "", 'n') => DSR(p(0, 0)),
+            (None, "", 'o') => DAQ(p(0, 0)),
+            (None, "", 'q') => DECLL,
+            (None, "", 'r') => DECSTBM(p(0, 1), p(1, 0)),
+            (None, "", 's') => DECSTRM(p(0, 1), p(1, 0)),
+            (None, "", 't') => DECSLPP(p(0, 66)),
+            (None, "", 'u') => DECSHTS,
+            (None, "", 'v') => DECSVTS,
+            (None, "", 'w') => DECSHORP(p(0, 0)),
+            (None, "", 'x') => DECREQTPARM,
+            (None, "", 'y') => DECTST(p(0, 2), p(1, 1)),
+            (None, "", 'z') => DECVERP(p(0, 0)),
+            (None, "", '|') => DECTTC(p(0, 0)),
+            (None, "", '}') => DECPRO,
+            (None, "", '~') => DECKEYS(p(0, 0)),
+            (None, " ", '@') => SL(p(0, 1)),
+            (None, " ", 'A') => SR(p(0, 1)),
+            (None, " ", 'B') => GSM(p(0, 100), p(1, 100)),
+            (None, " ", 'C') => GSS(p(0, 720)),
+            (None, " ", 'D') => FNT(p(0, 0), p(1, 1)),
+            (None, " ", 'E') => TSS(p(0, 720)),
+            (None, " ", 'F') => JFY(p(0, 0)),
+            (None, " ", 'G') => SPI(p(0, 720), p(1, 720)),
+            (None, " ", 'H') => QUAD(p(0, 0)),
+            (None, " ", 'q') => DECSCUSR(p(0, 0)),
+            (Some('?'), "", 'h') => DECSET(csi.params_or(0)),
+            (Some('?'), "", 'l') => DECRST(csi.params_or(0)),
+            (None, "$", 'p') => RQM(p(0, 0)),
+            (None, "!", 'p') => DECSTR,
+            (Some('>'), "", 'c') if p(0, 0) == 0 => DA2,
+            (Some('='), "", 'c') if p(0, 0) == 0 => DA3,
+            (Some('>'), "", 'q') if p(0, 0) == 0 => XTVERSION,
+            (Some('?'), "$", 'p') => DECRQM(p(0, 0)),
+            _ => Unrecognised(csi),
+        }
+    }
+}
+
+/// splits a parameter string into `;` separated groups of `:` separated sub-parameters\
+/// empty (sub-)parameters are `None`, and those too large for a `u32` saturate
+fn split_params(input: &str) -> Vec<Vec<Option<u32>>> {
+    input
+        .split(';')
+        .map(|param| {
+            param
+                .split(':')
+                // the grammar only lets digits through, so a failure to parse is an overflow
+                .map(|sub| {
+                    sub.parse::<u32>()
+                        .ok()
+                        .or_else(|| (!sub.is_empty()).then_some(u32::MAX))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// interprets SGR parameter groups as a list of attribute changes\
+/// unknown or malformed parameters are skipped
+fn sgr_attributes(params: &[Vec<Option<u32>>]) -> Vec<SgrAttribute> {
+    let mut attributes = Vec::new();
+    let mut i = 0;
+    while i < params.len() {
+        let group = &params[i];
+        let sub = &group[1..];
+        i += 1;
+        let attribute = match group[0].unwrap_or(0) {
+            0 => Some(SgrAttribute::Reset),
+            1 => Some(SgrAttribute::Bold),
+            2 => Some(SgrAttribute::Faint),
+            3 => Some(SgrAttribute::Italic),
+            4 => match sub.first().copied().flatten() {
+                None | Some(1) => Some(SgrAttribute::Underline(Underline::Single)),
+                Some(0) => Some(SgrAttribute::NotUnderlined),
+                Some(2) => Some(SgrAttribute::Underline(Underline::Double)),
+                Some(3) => Some(SgrAttribute::Underline(Underline::Curly)),
+                Some(4) => Some(SgrAttribute::Underline(Underline::Dotted)),
+                Some(5) => Some(SgrAttribute::Underline(Underline::Dashed)),
+                Some(_) => None,
+            },
+            5 => Some(SgrAttribute::SlowBlink),
+            6 => Some(SgrAttribute::RapidBlink),
+            7 => Some(SgrAttribute::Inverse),
+            8 => Some(SgrAttribute::Conceal),
+            9 => Some(SgrAttribute::CrossedOut),
+            n @ 10..=19 => Some(SgrAttribute::Font((n - 10) as u8)),
+            20 => Some(SgrAttribute::Fraktur),
+            21 => Some(SgrAttribute::Underline(Underline::Double)),
+            22 => Some(SgrAttribute::NormalIntensity),
+            23 => Some(SgrAttribute::NotItalic),
+            24 => Some(SgrAttribute::NotUnderlined),
+            25 => Some(SgrAttribute::NotBlinking),
+            27 => Some(SgrAttribute::NotInverse),
+            28 => Some(SgrAttribute::Reveal),
+            29 => Some(SgrAttribute::NotCrossedOut),
+            n @ 30..=37 => Some(SgrAttribute::Foreground(Color::Indexed((n - 30) as u8))),
+            38 => extended_color(sub, params, &mut i).map(SgrAttribute::Foreground),
+            39 => Some(SgrAttribute::Foreground(Color::Default)),
+            n @ 40..=47 => Some(SgrAttribute::Background(Color::Indexed((n - 40) as u8))),
+            48 => extended_color(sub, params, &mut i).map(SgrAttribute::Background),
+            49 => Some(SgrAttribute::Background(Color::Default)),
+            53 => Some(SgrAttribute::Overlined),
+            55 => Some(SgrAttribute::NotOverlined),
+            58 => extended_color(sub, params, &mut i).map(SgrAttribute::UnderlineColor),
+            59 => Some(SgrAttribute::UnderlineColor(Color::Default)),
+            n @ 90..=97 => Some(SgrAttribute::Foreground(Color::Indexed((n - 82) as u8))),
+            n @ 100..=107 => Some(SgrAttribute::Background(Color::Indexed((n - 92) as u8))),
+            _ => None,
+        };
+        attributes.extend(attribute);
+    }
+    attributes
+}
+
+/// reads the color of an SGR 38, 48 or 58 parameter\
+/// `sub` holds the `:` form (`38:5:#`, `38:2::#:#:#` or `38:2:#:#:#`), otherwise the color is
+/// read from the `;` form (`38;5;#` or `38;2;#;#;#`) following `params[*i]`, advancing `i` past it
+fn extended_color(
+    sub: &[Option<u32>],
+    params: &[Vec<Option<u32>>],
+    i: &mut usize,
+) -> Option<Color> {
+    let component = |value: Option<u32>| u8::try_from(value.unwrap_or(0)).unwrap_or(u8::MAX);
+    if !sub.is_empty() {
+        return match sub[0] {
+            Some(5) => sub.get(1).map(|&index| Color::Indexed(component(index))),
+            Some(2) => {
+                let rgb = if sub.len() >= 5 {
+                    &sub[2..5]
+                } else {
+                    sub.get(1..4)?
+                };
+                Some(Color::Rgb(
+                    component(rgb[0]),
+                    component(rgb[1]),
+                    component(rgb[2]),
+                ))
+            }
+            _ => None,
+        };
+    }
+    let next = |offset: usize| params.get(*i + offset).and_then(|group| group[0]);
+    match next(0) {
+        Some(5) => {
+            let index = params.get(*i + 1).map(|group| component(group[0]));
+            *i = (*i + 2).min(params.len());
+            index.map(Color::Indexed)
+        }
+        Some(2) => {
+            let color = (*i + 3 < params.len())
+                .then(|| Color::Rgb(component(next(1)), component(next(2)), component(next(3))));
+            *i = (*i + 4).min(params.len());
+            color
+        }
+        _ => None,
+    }
 }
 
-one_ctlseq!(ICH, "@", 1, EscapeSequence::ICH);
-one_ctlseq!(CUU, "A", 1, EscapeSequence::CUU);
-one_ctlseq!(CUD, "B", 1, EscapeSequence::CUD);
-one_ctlseq!(CUF, "C", 1, EscapeSequence::CUF);
-one_ctlseq!(CUB, "D", 1, EscapeSequence::CUB);
-one_ctlseq!(CNL, "E", 1, EscapeSequence::CNL);
-one_ctlseq!(CPL, "F", 1, EscapeSequence::CPL);
-one_ctlseq!(CHA, "G", 1, EscapeSequence::CHA);
-two_ctlseq!(CUP, "H", 1, 1, EscapeSequence::CUP);
-one_ctlseq!(CHT, "I", 1, EscapeSequence::CHT);
-one_ctlseq!(ED, "J", 0, EscapeSequence::ED);
-one_ctlseq!(EL, "K", 0, EscapeSequence::EL);
-one_ctlseq!(IL, "L", 0, EscapeSequence::IL);
-one_ctlseq!(DL, "M", 0, EscapeSequence::DL);
-one_ctlseq!(EF, "N", 0, EscapeSequence::EF);
-one_ctlseq!(EA, "O", 0, EscapeSequence::EA);
-one_ctlseq!(DCH, "P", 1, EscapeSequence::DCH);
-one_ctlseq!(SEM, "Q", 0, EscapeSequence::SEM);
-tag_parser!(CPR, "[R", EscapeSequence::CPR);
-one_ctlseq!(SU, "S", 1, EscapeSequence::SU);
-one_ctlseq!(SD, "T", 1, EscapeSequence::SD);
-one_ctlseq!(NP, "U", 1, EscapeSequence::NP);
-one_ctlseq!(PP, "V", 1, EscapeSequence::PP);
-one_ctlseq!(CTC, "W", 0, EscapeSequence::CTC);
-one_ctlseq!(ECH, "X", 1, EscapeSequence::ECH);
-one_ctlseq!(CVT, "Y", 1, EscapeSequence::CVT);
-one_ctlseq!(CBT, "Z", 1, EscapeSequence::CBT);
-one_ctlseq!(HPA, "`", 0, EscapeSequence::HPA);
-one_ctlseq!(HPR, "a", 0, EscapeSequence::HPR);
-one_ctlseq!(REP, "b", 1, EscapeSequence::REP);
-tag_parser!(DA, "[c", EscapeSequence::DA);
-one_ctlseq!(VPA, "d", 0, EscapeSequence::VPA);
-one_ctlseq!(VPR, "e", 0, EscapeSequence::VPR);
-two_ctlseq!(HVP, "f", 1, 1, EscapeSequence::HVP);
-one_ctlseq!(TBC, "g", 0, EscapeSequence::TBC);
-one_ctlseq!(SM, "h", 0, EscapeSequence::SM);
-one_ctlseq!(MC, "i", 0, EscapeSequence::MC);
-one_ctlseq!(PageFormatSelect, "j", 0, EscapeSequence::PageFormatSelect);
-tag_parser!(RM, "[l", EscapeSequence::RM);
-// TODO SGR takes 5 parameters
-one_ctlseq!(DSR, "n", 0, EscapeSequence::DSR);
-one_ctlseq!(DAQ, "o", 0, EscapeSequence::DAQ);
-tag_parser!(DECLL, "[q", EscapeSequence::DECLL);
-two_ctlseq!(DECSTBM, "r", 1, 1, EscapeSequence::DECSTBM);
-two_ctlseq!(DECSTRM, "s", 1, 1, EscapeSequence::DECSTRM);
-one_ctlseq!(DECSLPP, "t", 66, EscapeSequence::DECSLPP);
-// TODO DECSHTS takes many parameters
-// TODO DECSVTS takes many parameters
-one_ctlseq!(DECSHORP, "w", 0, EscapeSequence::DECSHORP);
-// TODO DECREQTPARM takes many parameters
-two_ctlseq!(DECTST, "y", 2, 1, EscapeSequence::DECTST);
-one_ctlseq!(DECVERP, "z", 0, EscapeSequence::DECVERP);
-one_ctlseq!(DECTTC, "!", 0, EscapeSequence::DECTTC);
-// TODO DECPRO takes many parameters
-one_ctlseq!(DECKEYS, "~", 0, EscapeSequence::DECKEYS);
-// TODO DELETE needs reading into, what hex code do I need
-one_ctlseq!(SL, " @", 1, EscapeSequence::SL);
-one_ctlseq!(SR, " A", 1, EscapeSequence::SR);
-two_ctlseq!(GSM, " B", 100, 100, EscapeSequence::GSM);
-one_ctlseq!(GSS, " C", 720, EscapeSequence::GSS);
-two_ctlseq!(FNT, " D", 0, 1, EscapeSequence::FNT);
-one_ctlseq!(TSS, " E", 720, EscapeSequence::TSS);
-one_ctlseq!(JFY, " F", 0, EscapeSequence::JFY);
-two_ctlseq!(SPI, " G", 720, 720, EscapeSequence::SPI);
-one_ctlseq!(QUAD, " H", 0, EscapeSequence::QUAD);
+#[cfg(test)]
+mod tests {
+    use crate::enums::{AnsiParser as _, AnsiStreamParser};
+
+    use super::*;
+
+    /// the escape sequence `input` starts with
+    fn sequence(input: &str) -> EscapeSequence {
+        match input.ansi_parse().next() {
+            Some(Token::EscapeSequence(sequence)) => sequence,
+            other => panic!("not an escape sequence: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn sgr_extended_colors() {
+        use SgrAttribute::*;
+        assert_eq!(
+            sequence("\x1b[38;5;208m"),
+            EscapeSequence::SGR(vec![Foreground(Color::Indexed(208))])
+        );
+        assert_eq!(
+            sequence("\x1b[48;2;1;2;3m"),
+            EscapeSequence::SGR(vec![Background(Color::Rgb(1, 2, 3))])
+        );
+        assert_eq!(
+            sequence("\x1b[38:2::1:2:3;1m"),
+            EscapeSequence::SGR(vec![Foreground(Color::Rgb(1, 2, 3)), Bold])
+        );
+        assert_eq!(
+            sequence("\x1b[4:3m"),
+            EscapeSequence::SGR(vec![Underline(super::Underline::Curly)])
+        );
+    }
+
+    #[test]
+    fn sgr_empty_and_overlong_params() {
+        use SgrAttribute::*;
+        assert_eq!(sequence("\x1b[m"), EscapeSequence::SGR(vec![Reset]));
+        assert_eq!(sequence("\x1b[;1m"), EscapeSequence::SGR(vec![Reset, Bold]));
+        assert_eq!(
+            sequence("\x1b[1;2;3;5;7;8;9m"),
+            EscapeSequence::SGR(vec![
+                Bold, Faint, Italic, SlowBlink, Inverse, Conceal, CrossedOut
+            ])
+        );
+        assert_eq!(
+            sequence("\x1b[99999999999;1m"),
+            EscapeSequence::SGR(vec![Bold])
+        );
+        assert_eq!(sequence("\x1b[99999999999A"), EscapeSequence::CUU(u32::MAX));
+    }
+
+    #[test]
+    fn csi_markers_and_intermediates() {
+        assert_eq!(sequence("\x1b[?1049h"), EscapeSequence::DECSET(vec![1049]));
+        assert_eq!(sequence("\x1b[?1;25l"), EscapeSequence::DECRST(vec![1, 25]));
+        assert_eq!(sequence("\x1b[>c"), EscapeSequence::DA2);
+        assert_eq!(sequence("\x1b[ q"), EscapeSequence::DECSCUSR(0));
+        assert_eq!(sequence("\x1b[2;3H"), EscapeSequence::CUP(2, 3));
+        assert_eq!(sequence("\x1b[H"), EscapeSequence::CUP(1, 1));
+    }
+
+    #[test]
+    fn bel_ends_only_osc() {
+        assert_eq!(
+            "\x1b]2;title\x07x".ansi_parse().collect::<Vec<_>>(),
+            vec![
+                Token::OperatingSystemCommand(OperatingSystemCommand::SetWindowTitle(
+                    String::from("title")
+                )),
+                Token::Text("x"),
+            ]
+        );
+        assert_eq!(
+            "\x1bPa\x07b\x1b\\".ansi_parse().collect::<Vec<_>>(),
+            vec![Token::ControlString(ControlString::DCS(String::from(
+                "a\x07b"
+            )))]
+        );
+    }
+
+    #[test]
+    fn can_and_sub_discard_strings() {
+        assert_eq!(
+            "\x1bPabc\x18x".ansi_parse().collect::<Vec<_>>(),
+            vec![Token::C0(C0::CAN), Token::Text("x")]
+        );
+        assert_eq!(
+            "\x1b_abc\x1ax".ansi_parse().collect::<Vec<_>>(),
+            vec![Token::C0(C0::SUB), Token::Text("x")]
+        );
+        assert_eq!(
+            "\x1b]2;title\x18".ansi_parse().collect::<Vec<_>>(),
+            vec![Token::C0(C0::CAN)]
+        );
+    }
+
+    #[test]
+    fn can_discards_string_split_across_feeds() {
+        let mut parser = AnsiStreamParser::default();
+        assert_eq!(parser.feed(b"\x1bPab").collect::<Vec<_>>(), vec![]);
+        assert_eq!(
+            parser.feed(b"\x18x").collect::<Vec<_>>(),
+            vec![Token::C0(C0::CAN), Token::Text("x")]
+        );
+    }
+}